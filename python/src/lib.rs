@@ -0,0 +1,175 @@
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use rust_sokoban::canonical::level_hash;
+use rust_sokoban::components::BoxColor;
+use rust_sokoban::env::{Action, Observation, RewardConfig, SokobanEnv, StepInfo};
+use rust_sokoban::map;
+use rust_sokoban::solver::{self, Board};
+
+// A level in the map format, see the README
+#[pyclass(name = "Level")]
+#[derive(Clone)]
+struct Level {
+    level: map::Level,
+}
+
+#[pymethods]
+impl Level {
+    #[new]
+    fn new(map: &str) -> PyResult<Self> {
+        map::Level::try_parse(map)
+            .map(|level| Self { level })
+            .map_err(PyValueError::new_err)
+    }
+
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        Self::new(&std::fs::read_to_string(path)?)
+    }
+
+    #[getter]
+    fn rows(&self) -> usize {
+        self.level.rows()
+    }
+
+    #[getter]
+    fn cols(&self) -> usize {
+        self.level.cols()
+    }
+
+    // Stays the same when the map is padded or reformatted
+    fn hash(&self) -> u64 {
+        level_hash(&self.level)
+    }
+
+    // A solution with the fewest pushes in LURD notation, or None if there
+    // isn't one or it wasn't found in `max_states` positions. Solutions move
+    // one player, levels with more are an error.
+    #[pyo3(signature = (max_states = 1_000_000))]
+    fn solve(&self, py: Python<'_>, max_states: usize) -> PyResult<Option<String>> {
+        py.allow_threads(|| solver::solve(&self.level, max_states))
+            .map_err(PyValueError::new_err)
+    }
+
+    // Cells, by row, a box of this color can never be pushed to a goal from
+    fn dead_squares(&self, color: char) -> PyResult<Vec<Vec<bool>>> {
+        let board = Board::from_level(&self.level)
+            .ok_or_else(|| PyValueError::new_err("the level has no player"))?;
+        let dead = board.dead_squares(BoxColor(color));
+        Ok(dead.chunks(board.cols).map(<[bool]>::to_vec).collect())
+    }
+
+    fn __str__(&self) -> String {
+        self.level.to_map_string()
+    }
+}
+
+// Plays levels with the game's own systems. Actions are 0 to 3 for left, up,
+// right and down. Observations are indexed [channel][row][col].
+#[pyclass(name = "Env")]
+struct Env {
+    env: SokobanEnv,
+    started: bool,
+}
+
+#[pymethods]
+impl Env {
+    // `colors` are the box colors with fixed observation channels, like "RB".
+    // Rewards are set by keyword: step, push, box_on_goal, solved, deadlock,
+    // terminate_on_deadlock and max_steps (None for no limit).
+    #[new]
+    #[pyo3(signature = (colors = "", **config))]
+    fn new(colors: &str, config: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut reward = RewardConfig::default();
+        for (key, value) in config.into_iter().flatten() {
+            match key.extract::<String>()?.as_str() {
+                "step" => reward.step = value.extract()?,
+                "push" => reward.push = value.extract()?,
+                "box_on_goal" => reward.box_on_goal = value.extract()?,
+                "solved" => reward.solved = value.extract()?,
+                "deadlock" => reward.deadlock = value.extract()?,
+                "terminate_on_deadlock" => reward.terminate_on_deadlock = value.extract()?,
+                "max_steps" => reward.max_steps = value.extract()?,
+                key => return Err(PyValueError::new_err(format!("unknown option {}", key))),
+            }
+        }
+
+        Ok(Self {
+            env: SokobanEnv::new(reward, colors.chars().map(BoxColor).collect()),
+            started: false,
+        })
+    }
+
+    fn channel_names(&self) -> Vec<String> {
+        self.env.channel_names()
+    }
+
+    fn reset(&mut self, level: &Level) -> PyResult<Vec<Vec<Vec<f32>>>> {
+        let observation = self
+            .env
+            .reset(&level.level)
+            .map_err(PyValueError::new_err)?;
+        self.started = true;
+        Ok(nested(&observation))
+    }
+
+    // Returns (observation, reward, done, info)
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: usize,
+    ) -> PyResult<(Vec<Vec<Vec<f32>>>, f32, bool, Bound<'py, PyDict>)> {
+        if !self.started {
+            return Err(PyRuntimeError::new_err("reset must be called first"));
+        }
+        let action = Action::from_index(action)
+            .ok_or_else(|| PyValueError::new_err("actions are 0 to 3"))?;
+
+        let (observation, reward, done, info) = self.env.step(action);
+        Ok((nested(&observation), reward, done, info_dict(py, &info)?))
+    }
+
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        info_dict(py, self.env.info())
+    }
+
+    // A box is stuck somewhere it can never be pushed to a goal from
+    fn is_deadlocked(&self) -> bool {
+        self.env.info().deadlocked
+    }
+}
+
+fn nested(observation: &Observation) -> Vec<Vec<Vec<f32>>> {
+    observation
+        .data
+        .chunks(observation.rows * observation.cols)
+        .map(|channel| {
+            channel
+                .chunks(observation.cols)
+                .map(<[f32]>::to_vec)
+                .collect()
+        })
+        .collect()
+}
+
+fn info_dict<'py>(py: Python<'py>, info: &StepInfo) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("moves", info.moves)?;
+    dict.set_item("pushes", info.pushes)?;
+    dict.set_item("boxes", info.boxes)?;
+    dict.set_item("boxes_on_goals", info.boxes_on_goals)?;
+    dict.set_item("solved", info.solved)?;
+    dict.set_item("deadlocked", info.deadlocked)?;
+    dict.set_item("truncated", info.truncated)?;
+    Ok(dict)
+}
+
+#[pymodule]
+fn sokoban(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Level>()?;
+    module.add_class::<Env>()?;
+    Ok(())
+}
@@ -20,7 +20,7 @@ impl AudioStore {
 
 pub fn initialize_sounds(world: &World, context: &mut Context) {
     let mut audio_store = world.write_resource::<AudioStore>();
-    let sounds = ["correct", "incorrect", "wall"];
+    let sounds = ["correct", "door", "incorrect", "pit", "teleport", "wall"];
 
     for sound in sounds.iter() {
         let sound_name = sound.to_string();
@@ -0,0 +1,120 @@
+use ggez::{filesystem, Context};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::canonical::level_hash;
+use crate::map::Level;
+
+const LEVELS_DIR: &str = "/levels";
+const PROGRESS_FILE: &str = "/progress.txt";
+
+pub struct LevelEntry {
+    pub name: String,
+    pub level: Level,
+    pub hash: u64,
+}
+
+// All the levels that can be picked from the level select screen, and which
+// of them have been solved. Solved levels are tracked by their level hash so
+// renaming or reformatting a level file keeps its progress.
+#[derive(Default)]
+pub struct Campaign {
+    pub levels: Vec<LevelEntry>,
+    pub solved: HashSet<u64>,
+}
+
+impl Campaign {
+    pub fn load(context: &mut Context) -> Self {
+        let mut paths: Vec<_> = filesystem::read_dir(context, LEVELS_DIR)
+            .expect("expected levels directory")
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        // Levels saved from the editor live in the user directory, which is
+        // listed alongside the resources
+        paths.sort();
+        paths.dedup();
+
+        // A level that doesn't read or parse is left out rather than taking
+        // the whole game down with it
+        let levels = paths
+            .iter()
+            .filter_map(|path| {
+                let level = read_string(context, path)
+                    .ok_or_else(|| "could not read it".to_string())
+                    .and_then(|map| Level::try_parse(&map));
+                let level = match level {
+                    Ok(level) => level,
+                    Err(error) => {
+                        eprintln!("Skipped level {}: {}", path.display(), error);
+                        return None;
+                    }
+                };
+                Some(LevelEntry {
+                    name: path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    hash: level_hash(&level),
+                    level,
+                })
+            })
+            .collect();
+
+        let solved = read_string(context, Path::new(PROGRESS_FILE))
+            .map(|progress| {
+                progress
+                    .lines()
+                    .filter_map(|line| u64::from_str_radix(line.trim(), 16).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { levels, solved }
+    }
+
+    pub fn is_solved(&self, index: usize) -> bool {
+        self.solved.contains(&self.levels[index].hash)
+    }
+
+    pub fn mark_solved(&mut self, context: &mut Context, index: usize) {
+        if !self.solved.insert(self.levels[index].hash) {
+            return;
+        }
+
+        let progress: String = self
+            .solved
+            .iter()
+            .map(|hash| format!("{:016x}\n", hash))
+            .collect();
+        let saved = filesystem::create(context, PROGRESS_FILE)
+            .and_then(|mut file| Ok(file.write_all(progress.as_bytes())?));
+        if let Err(error) = saved {
+            println!("Could not save progress: {}", error);
+        }
+    }
+
+    // Whether the level has a player for each of `seats` co-op players
+    pub fn has_seats_for(&self, index: usize, seats: usize) -> bool {
+        self.levels[index].level.player_count() >= seats
+    }
+
+    // The first level after `index` that `seats` co-op players can play
+    pub fn next_level(&self, index: usize, seats: usize) -> Option<usize> {
+        (index + 1..self.levels.len()).find(|next| self.has_seats_for(*next, seats))
+    }
+
+    // The largest level, used to size the window
+    pub fn max_size(&self) -> (usize, usize) {
+        self.levels.iter().fold((0, 0), |(rows, cols), entry| {
+            (rows.max(entry.level.rows()), cols.max(entry.level.cols()))
+        })
+    }
+}
+
+fn read_string(context: &mut Context, path: &Path) -> Option<String> {
+    let mut file = filesystem::open(context, path).ok()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    Some(contents)
+}
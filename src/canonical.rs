@@ -0,0 +1,159 @@
+use crate::components::Gate;
+use crate::map::{Level, Tile};
+use std::collections::VecDeque;
+
+// Canonical form of a level, independent of the file it came from. The floor
+// outside of the walls is removed, the grid is trimmed to its bounding box and
+// padded to a rectangle, so whitespace and decoration don't change identity.
+// Color declarations only change how boxes look, so they are left out.
+pub fn canonical_level(level: &Level) -> Level {
+    let rows = level.rows() + 2;
+    let cols = level.cols() + 2;
+
+    // Surround the level with a ring of nothing so the flood fill below can
+    // start from a single corner and reach everything outside the walls.
+    let mut tiles = vec![vec![Tile::Nothing; cols]; rows];
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            tiles[y + 1][x + 1] = *tile;
+        }
+    }
+
+    let is_outside = |tile: &Tile| matches!(tile, Tile::Nothing | Tile::Floor);
+    let mut visited = vec![vec![false; cols]; rows];
+    let mut queue = VecDeque::from(vec![(0, 0)]);
+    visited[0][0] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        tiles[y][x] = Tile::Nothing;
+
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx < cols && ny < rows && !visited[ny][nx] && is_outside(&tiles[ny][nx]) {
+                visited[ny][nx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    trim(tiles)
+}
+
+// The canonical level written out in the map format
+pub fn canonical_form(level: &Level) -> String {
+    canonical_level(level).to_map_string()
+}
+
+// Stable hash of the canonical form. This must not change between builds or
+// platforms, so we use FNV-1a rather than the std hasher.
+pub fn level_hash(level: &Level) -> u64 {
+    fnv1a(canonical_form(level).as_bytes())
+}
+
+// Like `level_hash`, but levels that are rotations or reflections of each
+// other hash the same. The smallest canonical form of the 8 variants is used.
+pub fn level_hash_symmetric(level: &Level) -> u64 {
+    let mut variant = canonical_level(level);
+    let mut forms = Vec::with_capacity(8);
+
+    for _ in 0..4 {
+        forms.push(variant.to_map_string());
+        forms.push(mirror(&variant).to_map_string());
+        variant = rotate(&variant);
+    }
+
+    fnv1a(forms.iter().min().expect("expected forms").as_bytes())
+}
+
+fn trim(tiles: Vec<Vec<Tile>>) -> Level {
+    let used = |tile: &Tile| *tile != Tile::Nothing;
+    let rows_used = tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| row.iter().any(used))
+        .map(|(y, _)| y);
+    let cols_used = tiles.iter().flat_map(|row| {
+        row.iter()
+            .enumerate()
+            .filter(|(_, t)| used(t))
+            .map(|(x, _)| x)
+    });
+
+    let (top, bottom) = match (rows_used.clone().min(), rows_used.max()) {
+        (Some(top), Some(bottom)) => (top, bottom),
+        _ => return Level::new(vec![]),
+    };
+    let left = cols_used.clone().min().unwrap_or(0);
+    let right = cols_used.max().unwrap_or(0);
+
+    Level::new(
+        tiles[top..=bottom]
+            .iter()
+            .map(|row| row[left..=right].to_vec())
+            .collect(),
+    )
+}
+
+// Rotate a rectangular level 90 degrees clockwise, gates that pointed left
+// point up
+fn rotate(level: &Level) -> Level {
+    let (rows, cols) = (level.rows(), level.cols());
+
+    Level::new(
+        (0..cols)
+            .map(|x| {
+                (0..rows)
+                    .rev()
+                    .map(|y| turn_gate(level.tiles[y][x], |direction| (direction + 1) % 4))
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+// Mirror a level left to right, gates that pointed left point right
+fn mirror(level: &Level) -> Level {
+    Level::new(
+        level
+            .tiles
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .rev()
+                    .map(|tile| turn_gate(*tile, |direction| [2, 1, 0, 3][direction]))
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+// Move the directions of a gate on a tile, in LURD order, to where `turn`
+// takes them
+fn turn_gate(tile: Tile, turn: fn(usize) -> usize) -> Tile {
+    let turned = |gate: Gate| {
+        let mut directions = [false; 4];
+        for (direction, open) in gate.directions.iter().enumerate() {
+            directions[turn(direction)] = *open;
+        }
+        Gate { directions, ..gate }
+    };
+    match tile {
+        Tile::Gate(gate) => Tile::Gate(turned(gate)),
+        Tile::BoxOnGate(color, gate) => Tile::BoxOnGate(color, turned(gate)),
+        Tile::PlayerOnGate(gate) => Tile::PlayerOnGate(turned(gate)),
+        tile => tile,
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(PRIME)
+    })
+}
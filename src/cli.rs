@@ -0,0 +1,230 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use specs::{World, WorldExt};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::components::register_components;
+use crate::constants::MULTIPLIER;
+use crate::generator::{generate, GeneratorConfig};
+use crate::map::{load_map, Level};
+use crate::replay::{replay, save_gif};
+use crate::resources::register_resources;
+use crate::screenshot::{Screenshot, RESOURCES_DIR};
+use crate::solver::solve;
+use crate::svg::{level_svg, SvgOptions};
+use crate::tui;
+
+// How far the solver searches when a command isn't given a solution
+const SOLVER_STATES: usize = 1_000_000;
+
+// Commands that run without opening a window. Returns false if the arguments
+// aren't a command, in which case the game starts as usual.
+pub fn run_command(args: &[String]) -> bool {
+    match args.first().map(String::as_str) {
+        Some("generate") => {
+            generate_command(&args[1..]);
+            true
+        }
+        Some("replay") => {
+            replay_command(&args[1..]);
+            true
+        }
+        Some("screenshot") => {
+            screenshot_command(&args[1..]);
+            true
+        }
+        Some("svg") => {
+            svg_command(&args[1..]);
+            true
+        }
+        Some("tui") => {
+            tui_command(&args[1..]);
+            true
+        }
+        _ => false,
+    }
+}
+
+// generate [rows] [cols] [boxes] [colors] [seed]
+//
+// Prints a new level in the map format. The solution used to rate it goes to
+// stderr so the output can be saved straight to a level file.
+fn generate_command(args: &[String]) {
+    let number = |index: usize, default: u64| {
+        args.get(index)
+            .map(|arg| arg.parse().expect("expected a number"))
+            .unwrap_or(default)
+    };
+
+    let config = GeneratorConfig {
+        rows: number(0, 8) as usize,
+        cols: number(1, 8) as usize,
+        boxes: number(2, 3) as usize,
+        colors: number(3, 2) as usize,
+        attempts: 20,
+    };
+    let mut rng = match args.get(4) {
+        Some(_) => StdRng::seed_from_u64(number(4, 0)),
+        None => StdRng::from_entropy(),
+    };
+
+    match generate(&config, &mut rng) {
+        Some(generated) => {
+            println!("{}", generated.level.to_map_string());
+            eprintln!(
+                "moves: {}\tpushes: {}\tsolution: {}",
+                generated.difficulty(),
+                generated.pushes(),
+                generated.solution
+            );
+        }
+        None => eprintln!("Could not generate a level, try a larger size or fewer boxes"),
+    }
+}
+
+// tui [level number or file]
+//
+// Plays in the terminal instead of a window. Levels saved from the editor
+// aren't listed, they can be played by passing their file.
+fn tui_command(args: &[String]) {
+    let (levels, index) = find_levels(args.first());
+    if let Err(error) = tui::run(&levels, index) {
+        eprintln!("Terminal error: {}", error);
+    }
+}
+
+// screenshot <level number or file> <output.png> [scale] [--hud]
+//
+// Draws the start of a level with the game's images and saves it as a PNG.
+// The scale defaults to the window's.
+fn screenshot_command(args: &[String]) {
+    let hud = args.iter().any(|arg| arg == "--hud");
+    let args: Vec<_> = args.iter().filter(|arg| *arg != "--hud").collect();
+    let (levels, index) = find_levels(args.first().copied());
+    let output = args.get(1).expect("expected an output file");
+    let scale = args
+        .get(2)
+        .map(|arg| arg.parse().expect("expected a number"))
+        .unwrap_or(MULTIPLIER as u32);
+
+    let mut world = World::new();
+    register_components(&mut world);
+    register_resources(&mut world);
+    load_map(&mut world, &levels[index].1);
+
+    let mut screenshot = Screenshot::new(Path::new(RESOURCES_DIR), scale);
+    screenshot.hud = hud;
+    match screenshot.save(&world, Path::new(output)) {
+        Ok(_) => eprintln!("Saved {}", output),
+        Err(error) => eprintln!("Could not save: {}", error),
+    }
+}
+
+// The solver's solution, or None after saying why there isn't one. Solutions
+// only move one player, so levels with more are turned away rather than
+// searched with the others standing still.
+fn solve_level(level: &Level) -> Option<String> {
+    match solve(level, SOLVER_STATES) {
+        Ok(Some(solution)) => Some(solution),
+        Ok(None) => {
+            eprintln!("Could not find a solution");
+            None
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            None
+        }
+    }
+}
+
+// replay <level number or file> <output.gif> [solution] [delay ms] [scale]
+//
+// Saves an animated GIF of a solution in LURD notation being played. Without
+// a solution, or with "-", the solver finds one.
+fn replay_command(args: &[String]) {
+    let (levels, index) = find_levels(args.first());
+    let level = &levels[index].1;
+    let output = args.get(1).expect("expected an output file");
+    let number = |index: usize, default: u32| {
+        args.get(index)
+            .map(|arg| arg.parse().expect("expected a number"))
+            .unwrap_or(default)
+    };
+    let delay = Duration::from_millis(number(3, 200) as u64);
+
+    let solution = match args.get(2).map(String::as_str) {
+        Some(solution) if solution != "-" => solution.to_string(),
+        _ => match solve_level(level) {
+            Some(solution) => solution,
+            None => return,
+        },
+    };
+
+    let mut screenshot = Screenshot::new(Path::new(RESOURCES_DIR), number(4, 1));
+    screenshot.hud = true;
+    let (frames, solved) = match replay(level, &solution, &mut screenshot, delay) {
+        Ok(replayed) => replayed,
+        Err(error) => {
+            eprintln!("{}", error);
+            return;
+        }
+    };
+    if !solved {
+        eprintln!("The solution doesn't solve the level, saving it anyway");
+    }
+
+    match save_gif(frames, delay, Path::new(output)) {
+        Ok(_) => eprintln!("Saved {}", output),
+        Err(error) => eprintln!("Could not save: {}", error),
+    }
+}
+
+// svg <level number or file> <output.svg> [solution] [--coordinates]
+//
+// Saves a vector drawing of a level, for print. A solution in LURD notation
+// is drawn over it, "-" draws the solver's.
+fn svg_command(args: &[String]) {
+    let coordinates = args.iter().any(|arg| arg == "--coordinates");
+    let args: Vec<_> = args.iter().filter(|arg| *arg != "--coordinates").collect();
+    let (levels, index) = find_levels(args.first().copied());
+    let level = &levels[index].1;
+    let output = args.get(1).expect("expected an output file");
+
+    let solution = match args.get(2).map(|arg| arg.as_str()) {
+        Some("-") => match solve_level(level) {
+            Some(solution) => Some(solution),
+            None => return,
+        },
+        solution => solution.map(str::to_string),
+    };
+
+    let options = SvgOptions {
+        coordinates,
+        solution,
+    };
+    match fs::write(output, level_svg(level, &options)) {
+        Ok(_) => eprintln!("Saved {}", output),
+        Err(error) => eprintln!("Could not save: {}", error),
+    }
+}
+
+// The shipped levels and the index of the one picked by `arg`, or just the
+// level in `arg` if it's a file. Starts from the first level without one.
+fn find_levels(arg: Option<&String>) -> (Vec<(String, Level)>, usize) {
+    match arg {
+        Some(arg) if Path::new(arg).is_file() => (vec![tui::load_level(Path::new(arg))], 0),
+        Some(arg) => {
+            let levels = tui::load_levels();
+            let index = arg
+                .parse::<usize>()
+                .ok()
+                .filter(|number| (1..=levels.len()).contains(number))
+                .expect("expected a level number or file")
+                - 1;
+            (levels, index)
+        }
+        None => (tui::load_levels(), 0),
+    }
+}
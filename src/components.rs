@@ -1,19 +1,34 @@
 use specs::{Component, NullStorage, VecStorage, World, WorldExt};
 use std::fmt;
+use std::time::Duration;
+
+// A coordinate on the map
+pub type Coord = u32;
 
 // Components
 #[derive(Debug, Component, Clone, Copy)]
 #[storage(VecStorage)]
 pub struct Position {
-    pub x: u8,
-    pub y: u8,
+    pub x: Coord,
+    pub y: Coord,
     pub z: u8,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 #[storage(VecStorage)]
 pub struct Renderable {
     pub paths: Vec<String>,
+    // Drawn instead when there is no image at the path
+    pub fallback: Option<Tinted>,
+    // How many times the image is turned clockwise by 90 degrees
+    pub quarter_turns: u8,
+}
+
+// An image drawn multiplied by a color
+#[derive(Clone)]
+pub struct Tinted {
+    pub path: String,
+    pub rgb: [u8; 3],
 }
 
 pub enum RenderableKind {
@@ -23,11 +38,35 @@ pub enum RenderableKind {
 
 impl Renderable {
     pub fn new_static(path: String) -> Self {
-        Self { paths: vec![path] }
+        Self {
+            paths: vec![path],
+            fallback: None,
+            quarter_turns: 0,
+        }
     }
 
     pub fn new_animated(paths: Vec<String>) -> Self {
-        Self { paths }
+        Self {
+            paths,
+            fallback: None,
+            quarter_turns: 0,
+        }
+    }
+
+    // Use the image at `path` if there is one, otherwise the tinted fallback
+    pub fn new_tinted(path: String, fallback: Tinted) -> Self {
+        Self {
+            paths: vec![path],
+            fallback: Some(fallback),
+            quarter_turns: 0,
+        }
+    }
+
+    pub fn rotated(self, quarter_turns: u8) -> Self {
+        Self {
+            quarter_turns: quarter_turns % 4,
+            ..self
+        }
     }
 
     pub fn kind(&self) -> RenderableKind {
@@ -38,6 +77,26 @@ impl Renderable {
         }
     }
 
+    // The image to show after `delta` time has gone by
+    pub fn frame(&self, delta: Duration) -> String {
+        let path_index = match self.kind() {
+            RenderableKind::Static => {
+                // We only have one image, so we just return that
+                0
+            }
+            RenderableKind::Animated => {
+                // If we have multiple, we want to select the right one based on the delta time.
+                // First we get the delta in milliseconds, we % by 1000 to get the milliseconds
+                // only and finally we divide by 250 to get a number between 0 and 4. If it's 4
+                // we technically are on the next iteration of the loop (or on 0), but we will let
+                // the renderable handle this logic of wrapping frames.
+                ((delta.as_millis() % 1000) / 250) as usize
+            }
+        };
+
+        self.path(path_index)
+    }
+
     pub fn path(&self, path_index: usize) -> String {
         // If we get asked for a path that is larger than the
         // number of paths we actually have, we simply mod the index
@@ -54,19 +113,40 @@ pub struct Wall {}
 #[storage(VecStorage)]
 pub struct Player {}
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
-pub enum BoxColor {
-    Red,
-    Blue,
+// The player that moves when a level has more than one, switched with Tab
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub struct Active;
+
+impl Active {
+    // Drawn over the active player when there's more than one to choose from
+    pub const IMAGE: &'static str = "/images/active.png";
+    pub const Z: u8 = 11;
+}
+
+// Which co-op player controls a player entity, each has their own keys and
+// color
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Seat(pub usize);
+
+// Box colors are identified by the character following `B` or `S` in a map
+// token, their name and tint come from the level's palette.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct BoxColor(pub char);
+
+impl BoxColor {
+    // A neutral box fits on any spot, and a neutral spot takes any box
+    pub const ANY: BoxColor = BoxColor('*');
+
+    pub fn accepts(&self, other: BoxColor) -> bool {
+        *self == other || *self == BoxColor::ANY || other == BoxColor::ANY
+    }
 }
 
 impl fmt::Display for BoxColor {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_str(match self {
-            BoxColor::Red => "red",
-            BoxColor::Blue => "blue",
-        })?;
-        Ok(())
+        write!(fmt, "{}", self.0)
     }
 }
 
@@ -82,6 +162,149 @@ pub struct BoxSpot {
     pub color: BoxColor,
 }
 
+// One of a pair of teleporters, identified by the character following `T` in
+// a map token. Whatever enters it comes out at the other one, `exit`, unless
+// something is in the way. Teleporters without a partner do nothing.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Teleporter {
+    pub id: char,
+    pub exit: Option<(Coord, Coord)>,
+}
+
+// Who a one-way gate holds to its directions, anything else goes through it
+// any way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateFor {
+    Everything,
+    Player,
+    Boxes,
+}
+
+// A floor that can only be entered moving in its directions, which are in
+// LURD order. In maps it's a `G` followed by arrows, `<`, `^`, `>` and `v`,
+// and `p` or `b` if it only holds the player or boxes back.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Gate {
+    pub directions: [bool; 4],
+    pub holds: GateFor,
+}
+
+impl Gate {
+    pub const ARROWS: [char; 4] = ['<', '^', '>', 'v'];
+
+    // Parse what follows the `G` of a token
+    pub fn parse(chars: &[char]) -> Option<Self> {
+        let mut gate = Gate {
+            directions: [false; 4],
+            holds: GateFor::Everything,
+        };
+        for (index, c) in chars.iter().enumerate() {
+            match Self::ARROWS.iter().position(|arrow| arrow == c) {
+                Some(direction) => gate.directions[direction] = true,
+                None if index + 1 == chars.len() && *c == 'p' => gate.holds = GateFor::Player,
+                None if index + 1 == chars.len() && *c == 'b' => gate.holds = GateFor::Boxes,
+                None => return None,
+            }
+        }
+
+        gate.directions.contains(&true).then_some(gate)
+    }
+
+    pub fn token(&self) -> String {
+        let arrows = Self::ARROWS
+            .iter()
+            .zip(self.directions)
+            .filter(|(_, allowed)| *allowed)
+            .map(|(arrow, _)| *arrow);
+        let holds = match self.holds {
+            GateFor::Everything => None,
+            GateFor::Player => Some('p'),
+            GateFor::Boxes => Some('b'),
+        };
+        std::iter::once('G').chain(arrows).chain(holds).collect()
+    }
+
+    // Whether something moving in `direction`, in LURD order, can come in
+    pub fn allows(&self, direction: usize, is_player: bool) -> bool {
+        let held = match self.holds {
+            GateFor::Everything => true,
+            GateFor::Player => is_player,
+            GateFor::Boxes => !is_player,
+        };
+        !held || self.directions[direction]
+    }
+}
+
+// A hole the player can't walk into. A box pushed into it falls in and is
+// gone, and the pit is filled so it can be walked over like a floor.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Pit {
+    pub filled: bool,
+}
+
+impl Pit {
+    pub fn image(&self) -> String {
+        if self.filled {
+            "/images/pit_filled.png".to_string()
+        } else {
+            "/images/pit.png".to_string()
+        }
+    }
+}
+
+// Holds the doors with the same id open while the player or a box is on it
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Plate {
+    pub id: char,
+}
+
+// Flips each time the player or a box moves onto it. The doors with the same
+// id are open while it's on.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+#[storage(VecStorage)]
+pub struct Switch {
+    pub id: char,
+    pub on: bool,
+}
+
+impl Switch {
+    pub fn image(&self) -> String {
+        if self.on {
+            "/images/switch_on.png".to_string()
+        } else {
+            "/images/switch.png".to_string()
+        }
+    }
+}
+
+// Shut like a wall unless a plate or switch with the same id holds it open.
+// A door that would shut on something stays open until it's left.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Door {
+    pub id: char,
+    pub open: bool,
+}
+
+impl Door {
+    pub fn image(&self) -> String {
+        if self.open {
+            "/images/door_open.png".to_string()
+        } else {
+            "/images/door.png".to_string()
+        }
+    }
+}
+
+// A floor the player and boxes slide across
+#[derive(Component, Default)]
+#[storage(NullStorage)]
+pub struct Ice;
+
 #[derive(Component, Default)]
 #[storage(NullStorage)]
 pub struct Movable;
@@ -95,9 +318,18 @@ pub fn register_components(world: &mut World) {
     world.register::<Position>();
     world.register::<Renderable>();
     world.register::<Player>();
+    world.register::<Active>();
+    world.register::<Seat>();
     world.register::<Wall>();
     world.register::<Box>();
     world.register::<BoxSpot>();
+    world.register::<Teleporter>();
+    world.register::<Ice>();
+    world.register::<Gate>();
+    world.register::<Pit>();
+    world.register::<Plate>();
+    world.register::<Switch>();
+    world.register::<Door>();
     world.register::<Movable>();
     world.register::<Immovable>();
 }
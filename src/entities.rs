@@ -1,15 +1,23 @@
 use crate::components::*;
-use specs::{Builder, World, WorldExt};
+use crate::palette::ColorDef;
+use crate::resources::{Gameplay, Grid, SeatCount};
+use specs::{Builder, Entity, Join, World, WorldExt};
+
+// Put an entity on the grid so systems can find it by its cell
+fn place(world: &mut World, entity: Entity, position: Position) {
+    world.write_resource::<Grid>().insert(entity, &position);
+}
 
 // Create a wall entity
 pub fn create_wall(world: &mut World, position: Position) {
-    world
+    let entity = world
         .create_entity()
         .with(Position { z: 10, ..position })
         .with(Renderable::new_static("/images/wall.png".to_string()))
         .with(Wall {})
         .with(Immovable)
         .build();
+    place(world, entity, position);
 }
 
 pub fn create_floor(world: &mut World, position: Position) {
@@ -20,31 +28,125 @@ pub fn create_floor(world: &mut World, position: Position) {
         .build();
 }
 
-pub fn create_box(world: &mut World, position: Position, color: BoxColor) {
-    world
+pub fn create_ice(world: &mut World, position: Position) {
+    let entity = world
+        .create_entity()
+        .with(Position { z: 6, ..position })
+        .with(Renderable::new_static("/images/ice.png".to_string()))
+        .with(Ice)
+        .build();
+    place(world, entity, position);
+}
+
+// The image and quarter turns for each way into a gate. The arrow image
+// points up and is turned to the other directions.
+pub fn gate_arrows(gate: &Gate) -> Vec<(String, u8)> {
+    let path = match gate.holds {
+        GateFor::Everything => "/images/gate.png",
+        GateFor::Player => "/images/gate_player.png",
+        GateFor::Boxes => "/images/gate_boxes.png",
+    };
+    (0..4)
+        .filter(|direction| gate.directions[*direction])
+        .map(|direction| (path.to_string(), (direction as u8 + 3) % 4))
+        .collect()
+}
+
+// The gate, and an arrow for each way it can be entered
+pub fn create_gate(world: &mut World, position: Position, gate: Gate) {
+    for (path, quarter_turns) in gate_arrows(&gate) {
+        world
+            .create_entity()
+            .with(Position { z: 7, ..position })
+            .with(Renderable::new_static(path).rotated(quarter_turns))
+            .build();
+    }
+
+    let entity = world.create_entity().with(position).with(gate).build();
+    place(world, entity, position);
+}
+
+pub fn create_pit(world: &mut World, position: Position) {
+    let pit = Pit { filled: false };
+    let entity = world
+        .create_entity()
+        .with(Position { z: 6, ..position })
+        .with(Renderable::new_static(pit.image()))
+        .with(pit)
+        .build();
+    place(world, entity, position);
+}
+
+pub fn create_plate(world: &mut World, position: Position, id: char) {
+    let entity = world
+        .create_entity()
+        .with(Position { z: 9, ..position })
+        .with(Renderable::new_static("/images/plate.png".to_string()))
+        .with(Plate { id })
+        .build();
+    place(world, entity, position);
+}
+
+pub fn create_switch(world: &mut World, position: Position, switch: Switch) {
+    let entity = world
+        .create_entity()
+        .with(Position { z: 9, ..position })
+        .with(Renderable::new_static(switch.image()))
+        .with(switch)
+        .build();
+    place(world, entity, position);
+}
+
+// Shut doors are immovable, the `DoorSystem` keeps that up to date
+pub fn create_door(world: &mut World, position: Position, id: char, open: bool) {
+    let door = Door { id, open };
+    let mut builder = world
+        .create_entity()
+        .with(Position { z: 8, ..position })
+        .with(Renderable::new_static(door.image()))
+        .with(door);
+    if !open {
+        builder = builder.with(Immovable);
+    }
+    let entity = builder.build();
+    place(world, entity, position);
+}
+
+pub fn create_box(world: &mut World, position: Position, color: &ColorDef) {
+    let entity = world
         .create_entity()
         .with(Position { z: 10, ..position })
-        .with(Renderable::new_static(
-            format!("/images/box_{}.png", color).to_string(),
+        .with(Renderable::new_tinted(
+            format!("/images/box_{}.png", color.name),
+            Tinted {
+                path: "/images/box.png".to_string(),
+                rgb: color.rgb,
+            },
         ))
-        .with(Box { color })
+        .with(Box { color: color.color })
         .with(Movable)
         .build();
+    place(world, entity, position);
 }
 
-pub fn create_box_spot(world: &mut World, position: Position, color: BoxColor) {
-    world
+pub fn create_box_spot(world: &mut World, position: Position, color: &ColorDef) {
+    let entity = world
         .create_entity()
         .with(Position { z: 9, ..position })
-        .with(Renderable::new_static(
-            format!("/images/box_spot_{}.png", color).to_string(),
+        .with(Renderable::new_tinted(
+            format!("/images/box_spot_{}.png", color.name),
+            Tinted {
+                path: "/images/box_spot.png".to_string(),
+                rgb: color.rgb,
+            },
         ))
-        .with(BoxSpot { color })
+        .with(BoxSpot { color: color.color })
         .build();
+    place(world, entity, position);
 }
 
 pub fn create_player(world: &mut World, position: Position) {
-    world
+    let entity = world
         .create_entity()
         .with(Position { z: 10, ..position })
         .with(Renderable::new_animated(vec![
@@ -55,4 +157,63 @@ pub fn create_player(world: &mut World, position: Position) {
         .with(Player {})
         .with(Movable)
         .build();
+    place(world, entity, position);
+}
+
+// The colors co-op players are drawn in, by seat
+const SEAT_COLORS: [[u8; 3]; 2] = [[70, 130, 220], [220, 110, 50]];
+
+// Give the first `seats` players in reading order to co-op players, each in
+// their own color. Nobody is active, every player moves with their own keys.
+// Returns how many were seated, fewer than `seats` if the level is short.
+pub fn seat_players(world: &mut World, seats: usize) -> usize {
+    let mut players: Vec<_> = {
+        let entities = world.entities();
+        let positions = world.read_storage::<Position>();
+        let players = world.read_storage::<Player>();
+        (&entities, &positions, &players)
+            .join()
+            .map(|(entity, position, _)| ((position.y, position.x), entity))
+            .collect()
+    };
+    players.sort();
+    let seats = seats.min(players.len());
+
+    world.write_storage::<Active>().clear();
+    for (seat, (_, entity)) in players.into_iter().take(seats).enumerate() {
+        world
+            .write_storage::<Seat>()
+            .insert(entity, Seat(seat))
+            .expect("expected to seat the player");
+        world
+            .write_storage::<Renderable>()
+            .insert(
+                entity,
+                Renderable::new_tinted(
+                    format!("/images/player_p{}.png", seat + 1),
+                    Tinted {
+                        path: "/images/player.png".to_string(),
+                        rgb: SEAT_COLORS[seat % SEAT_COLORS.len()],
+                    },
+                ),
+            )
+            .expect("expected to color the player");
+    }
+    world.write_resource::<Gameplay>().seats = vec![SeatCount::default(); seats];
+    seats
+}
+
+pub fn create_teleporter(
+    world: &mut World,
+    position: Position,
+    id: char,
+    exit: Option<(Coord, Coord)>,
+) {
+    let entity = world
+        .create_entity()
+        .with(Position { z: 9, ..position })
+        .with(Renderable::new_static("/images/teleporter.png".to_string()))
+        .with(Teleporter { id, exit })
+        .build();
+    place(world, entity, position);
 }
@@ -0,0 +1,302 @@
+use ggez::event::KeyCode;
+use serde::Deserialize;
+use specs::{Join, World, WorldExt};
+use std::collections::HashMap;
+
+use crate::components::*;
+use crate::headless::Headless;
+use crate::map::{Level, Tile};
+use crate::resources::{Gameplay, GameplayState, Grid};
+use crate::solver::{Board, DIRECTIONS};
+
+// The moves an agent can make, in the same LURD order as the solver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Left,
+    Up,
+    Right,
+    Down,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [Action::Left, Action::Up, Action::Right, Action::Down];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    // A move in LURD notation, pushes are the same move in upper case
+    pub fn from_lurd(c: char) -> Option<Self> {
+        DIRECTIONS
+            .iter()
+            .position(|(_, _, lurd)| *lurd == c.to_ascii_lowercase())
+            .and_then(Self::from_index)
+    }
+
+    pub fn key(self) -> KeyCode {
+        match self {
+            Action::Left => KeyCode::Left,
+            Action::Up => KeyCode::Up,
+            Action::Right => KeyCode::Right,
+            Action::Down => KeyCode::Down,
+        }
+    }
+}
+
+// How rewards are handed out, they add up over a step
+#[derive(Debug, Clone)]
+pub struct RewardConfig {
+    // Given every step, a small penalty makes shorter solutions better
+    pub step: f32,
+    pub push: f32,
+    // Given for every box put on a goal, and taken away when it comes off
+    pub box_on_goal: f32,
+    pub solved: f32,
+    // Given when a deadlock ends the episode
+    pub deadlock: f32,
+    // End the episode as soon as a box can't reach a goal any more
+    pub terminate_on_deadlock: bool,
+    pub max_steps: Option<u32>,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            step: -0.1,
+            push: 0.0,
+            box_on_goal: 1.0,
+            solved: 10.0,
+            deadlock: -10.0,
+            terminate_on_deadlock: true,
+            max_steps: Some(200),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StepInfo {
+    pub moves: u32,
+    pub pushes: u32,
+    pub boxes: usize,
+    pub boxes_on_goals: usize,
+    pub solved: bool,
+    pub deadlocked: bool,
+    // The episode ran out of steps
+    pub truncated: bool,
+}
+
+// A stack of grids, one per channel, flattened channel by channel and then
+// row by row. Cells are 1.0 when the thing the channel is for is there.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub channels: usize,
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<f32>,
+}
+
+impl Observation {
+    fn new(channels: usize, rows: usize, cols: usize) -> Self {
+        Self {
+            channels,
+            rows,
+            cols,
+            data: vec![0.0; channels * rows * cols],
+        }
+    }
+
+    pub fn get(&self, channel: usize, x: usize, y: usize) -> f32 {
+        self.data[(channel * self.rows + y) * self.cols + x]
+    }
+
+    fn set(&mut self, channel: usize, position: &Position) {
+        let index = (channel * self.rows + position.y as usize) * self.cols + position.x as usize;
+        self.data[index] = 1.0;
+    }
+}
+
+const WALL_CHANNEL: usize = 0;
+const PLAYER_CHANNEL: usize = 1;
+
+// Plays levels with the same systems as the game, without a window or sound,
+// one action at a time
+pub struct SokobanEnv {
+    pub config: RewardConfig,
+    // The box colors with channels in observations, in order
+    colors: Vec<BoxColor>,
+    game: Headless,
+    // Dead squares of each color, indexed like the solver's board
+    dead: HashMap<BoxColor, Vec<bool>>,
+    rows: usize,
+    cols: usize,
+    steps: u32,
+    // Where the last step left things, to reward what changed
+    info: StepInfo,
+}
+
+impl SokobanEnv {
+    // Observations have a box and a goal channel for each of `colors`, so
+    // they have the same shape across levels. Colors used by a level that
+    // aren't listed get channels added at the end on reset.
+    pub fn new(config: RewardConfig, colors: Vec<BoxColor>) -> Self {
+        Self {
+            config,
+            colors,
+            game: Headless::new(&Level::new(vec![])),
+            dead: HashMap::new(),
+            rows: 0,
+            cols: 0,
+            steps: 0,
+            info: StepInfo::default(),
+        }
+    }
+
+    // Walls, the player, then boxes and goals of each color
+    pub fn channel_names(&self) -> Vec<String> {
+        let mut names = vec!["wall".to_string(), "player".to_string()];
+        for color in self.colors.iter() {
+            names.push(format!("box {}", color));
+            names.push(format!("goal {}", color));
+        }
+        names
+    }
+
+    // Where the last step or reset left things
+    pub fn info(&self) -> &StepInfo {
+        &self.info
+    }
+
+    // The world being played, for drawing it
+    pub fn world(&self) -> &World {
+        &self.game.world
+    }
+
+    // Start playing `level`, which needs a player to move
+    pub fn reset(&mut self, level: &Level) -> Result<Observation, String> {
+        let board = Board::from_level(level).ok_or("the level has no player")?;
+
+        self.game = Headless::new(level);
+        self.rows = self.game.rows() as usize;
+        self.cols = self.game.cols() as usize;
+        self.steps = 0;
+
+        self.dead.clear();
+        for (_, color) in board.boxes.iter() {
+            self.dead
+                .entry(*color)
+                .or_insert_with(|| board.dead_squares(*color));
+        }
+        for tile in level.tiles.iter().flatten() {
+            let colors = match *tile {
+                Tile::BoxSpot(color) | Tile::PlayerOnSpot(color) => vec![color],
+                Tile::BoxOnSpot(color, spot) => vec![color, spot],
+                _ => tile.box_color().into_iter().collect(),
+            };
+            for color in colors {
+                if !self.colors.contains(&color) {
+                    self.colors.push(color);
+                }
+            }
+        }
+
+        let (observation, info) = self.observe();
+        self.info = info;
+        Ok(observation)
+    }
+
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, StepInfo) {
+        self.game.press(action.key());
+        self.steps += 1;
+
+        let (observation, mut info) = self.observe();
+        let config = &self.config;
+        let mut reward = config.step
+            + config.push * (info.pushes - self.info.pushes) as f32
+            + config.box_on_goal * (info.boxes_on_goals as f32 - self.info.boxes_on_goals as f32);
+        if info.solved {
+            reward += config.solved;
+        } else if info.deadlocked && config.terminate_on_deadlock {
+            reward += config.deadlock;
+        }
+
+        info.truncated = config.max_steps.is_some_and(|max| self.steps >= max);
+        let done =
+            info.solved || (info.deadlocked && config.terminate_on_deadlock) || info.truncated;
+        self.info = info.clone();
+        (observation, reward, done, info)
+    }
+
+    fn observe(&self) -> (Observation, StepInfo) {
+        let mut observation = Observation::new(2 + self.colors.len() * 2, self.rows, self.cols);
+        let channel = |color: BoxColor| {
+            self.colors
+                .iter()
+                .position(|c| *c == color)
+                .expect("expected a channel for the color")
+                * 2
+                + 2
+        };
+
+        let positions = self.game.world.read_storage::<Position>();
+        let walls = self.game.world.read_storage::<Wall>();
+        let doors = self.game.world.read_storage::<Door>();
+        let players = self.game.world.read_storage::<Player>();
+        let boxes = self.game.world.read_storage::<Box>();
+        let box_spots = self.game.world.read_storage::<BoxSpot>();
+        let grid = self.game.world.read_resource::<Grid>();
+        let gameplay = self.game.world.read_resource::<Gameplay>();
+
+        for (position, _wall) in (&positions, &walls).join() {
+            observation.set(WALL_CHANNEL, position);
+        }
+        // Shut doors are walls until they open
+        for (position, door) in (&positions, &doors).join() {
+            if !door.open {
+                observation.set(WALL_CHANNEL, position);
+            }
+        }
+        for (position, _player) in (&positions, &players).join() {
+            observation.set(PLAYER_CHANNEL, position);
+        }
+        let mut goals = 0;
+        for (position, box_spot) in (&positions, &box_spots).join() {
+            observation.set(channel(box_spot.color) + 1, position);
+            goals += 1;
+        }
+
+        let mut info = StepInfo {
+            moves: gameplay.moves_count,
+            pushes: gameplay.pushes_count,
+            solved: matches!(gameplay.state, GameplayState::Won),
+            ..StepInfo::default()
+        };
+        let mut dead_boxes = 0;
+        for (position, the_box) in (&positions, &boxes).join() {
+            observation.set(channel(the_box.color), position);
+
+            let on_goal = grid
+                .at(position.x, position.y)
+                .iter()
+                .filter_map(|entity| box_spots.get(*entity))
+                .any(|box_spot| box_spot.color.accepts(the_box.color));
+            let cell = position.y as usize * self.cols + position.x as usize;
+            info.boxes += 1;
+            if on_goal {
+                info.boxes_on_goals += 1;
+            } else if self.dead[&the_box.color][cell] {
+                dead_boxes += 1;
+            }
+        }
+        // Like the solver, boxes the goals don't need can be left anywhere
+        if dead_boxes > info.boxes.saturating_sub(goals) {
+            info.deadlocked = true;
+        }
+
+        if matches!(gameplay.state, GameplayState::Stuck) {
+            info.deadlocked = true;
+        }
+
+        (observation, info)
+    }
+}
@@ -1,23 +1,107 @@
+use crate::components::Position;
+use specs::Entity;
+
+// Events are written to an `EventChannel<Event>` resource. Every system that
+// cares about them keeps its own `ReaderId`, so they all see every event.
 #[derive(Debug)]
 pub enum Event {
+    // Fired once a level has been loaded
+    LevelStarted,
+
+    // Fired when every box spot has a box on it
+    LevelCompleted,
+
     // Fired when the player hits an obstacle like a wall
     PlayerHitObstacle,
 
+    // Fired when the player takes a step
+    PlayerMoved(PlayerMoved),
+
     // Fired when an entity is moved
     EntityMoved(EntityMoved),
 
+    // Fired when the player pushes a box
+    BoxPushed(BoxPushed),
+
     // Fired when the box is placed on a spot
     BoxPlacedOnSpot(BoxPlacedOnSpot),
-}
 
-pub type EntityId = u32;
+    // Fired when a box is pushed off a spot
+    BoxRemovedFromSpot(BoxRemovedFromSpot),
+
+    // Fired when the player or a box comes out of a teleporter
+    Teleported(Teleported),
+
+    // Fired when a box is pushed into a pit and fills it
+    BoxFellInPit(BoxFellInPit),
 
+    // Fired when a door opens or shuts
+    DoorToggled(DoorToggled),
+
+    // Fired when the last move is taken back
+    Undo(Undo),
+}
+
+// Events hold the whole entity, not just its id, so an entity that was
+// deleted since and had its id reused is noticed instead of acted on
 #[derive(Debug)]
 pub struct EntityMoved {
-    pub id: EntityId,
+    pub entity: Entity,
+    // The cell it moved onto, sliding over ice moves it a cell at a time
+    pub to: Position,
+}
+
+#[derive(Debug)]
+pub struct PlayerMoved {
+    pub entity: Entity,
+    pub from: Position,
+    pub to: Position,
+}
+
+#[derive(Debug)]
+pub struct BoxPushed {
+    pub entity: Entity,
+    pub from: Position,
+    pub to: Position,
 }
 
 #[derive(Debug)]
 pub struct BoxPlacedOnSpot {
+    pub entity: Entity,
     pub is_correct_spot: bool,
 }
+
+#[derive(Debug)]
+pub struct BoxRemovedFromSpot {
+    pub entity: Entity,
+}
+
+#[derive(Debug)]
+pub struct Teleported {
+    pub entity: Entity,
+    // The teleporter that was entered
+    pub entrance: Position,
+    // Where the entity came out, its partner
+    pub exit: Position,
+}
+
+#[derive(Debug)]
+pub struct BoxFellInPit {
+    // The box, which is deleted
+    pub entity: Entity,
+    pub pit: Position,
+}
+
+#[derive(Debug)]
+pub struct DoorToggled {
+    pub entity: Entity,
+    pub open: bool,
+}
+
+#[derive(Debug)]
+pub struct Undo {
+    // How many boxes the move taken back pushed
+    pub pushes: u32,
+    // The player who made it
+    pub player: Option<Entity>,
+}
@@ -0,0 +1,169 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::VecDeque;
+
+use crate::components::BoxColor;
+use crate::map::{Level, Tile};
+use crate::solver::{solve, DIRECTIONS};
+
+const COLORS: [BoxColor; 4] = [BoxColor('R'), BoxColor('B'), BoxColor('G'), BoxColor('Y')];
+
+// How much of the inside of the room is turned into walls
+const WALL_DENSITY: f64 = 0.2;
+
+// Give up rating a level if solving it takes too long
+const MAX_SOLVER_STATES: usize = 200_000;
+
+pub struct GeneratorConfig {
+    pub rows: usize,
+    pub cols: usize,
+    pub boxes: usize,
+    pub colors: usize,
+    // Levels generated before keeping the hardest one
+    pub attempts: usize,
+}
+
+pub struct GeneratedLevel {
+    pub level: Level,
+    pub solution: String,
+}
+
+impl GeneratedLevel {
+    // Difficulty is rated by the length of the solution found by the solver
+    pub fn difficulty(&self) -> usize {
+        self.solution.len()
+    }
+
+    pub fn pushes(&self) -> usize {
+        self.solution
+            .chars()
+            .filter(|c| c.is_ascii_uppercase())
+            .count()
+    }
+}
+
+// Generate a solvable level. A room is carved out first, then boxes are put on
+// their goals and pulled backwards by a player walking around randomly, so
+// the result can always be pushed back. Returns None if no attempt worked.
+pub fn generate<R: Rng>(config: &GeneratorConfig, rng: &mut R) -> Option<GeneratedLevel> {
+    (0..config.attempts)
+        .filter_map(|_| attempt(config, rng))
+        .max_by_key(|generated| generated.difficulty())
+}
+
+fn attempt<R: Rng>(config: &GeneratorConfig, rng: &mut R) -> Option<GeneratedLevel> {
+    let (rows, cols) = (config.rows, config.cols);
+    let floor = room(rows, cols, rng)?;
+    if floor.len() < config.boxes * 2 + 1 {
+        return None;
+    }
+
+    let colors = &COLORS[..config.colors.clamp(1, COLORS.len())];
+    let mut cells = floor.clone();
+    cells.shuffle(rng);
+    let goals: Vec<(usize, BoxColor)> = cells[..config.boxes]
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| (*cell, colors[i % colors.len()]))
+        .collect();
+    let mut boxes = goals.clone();
+    let mut player = cells[config.boxes];
+
+    let is_floor = |cell: usize| floor.contains(&cell);
+    let step = |cell: usize, dx: isize, dy: isize| {
+        let x = (cell % cols) as isize + dx;
+        let y = (cell / cols) as isize + dy;
+        Some(y as usize * cols + x as usize).filter(|next| is_floor(*next))
+    };
+
+    // Keep walking until nothing is left on a goal, so levels don't start
+    // partly solved
+    let on_goal = |boxes: &[(usize, BoxColor)], player: usize| {
+        goals
+            .iter()
+            .any(|(goal, _)| *goal == player || boxes.iter().any(|(cell, _)| cell == goal))
+    };
+
+    // Walk the player around, pulling the box behind it most of the time
+    let steps = rows * cols * 4;
+    for step_count in 0..steps * 2 {
+        if step_count >= steps && !on_goal(&boxes, player) {
+            break;
+        }
+
+        let (dx, dy, _) = *DIRECTIONS.choose(rng).expect("expected direction");
+        let next = match step(player, dx, dy) {
+            Some(next) if !boxes.iter().any(|(cell, _)| *cell == next) => next,
+            _ => continue,
+        };
+
+        let behind = step(player, -dx, -dy);
+        if let Some(pulled) = boxes.iter_mut().find(|(cell, _)| Some(*cell) == behind) {
+            if rng.gen_bool(0.7) {
+                pulled.0 = player;
+            }
+        }
+        player = next;
+    }
+    if on_goal(&boxes, player) {
+        return None;
+    }
+
+    let mut tiles = vec![vec![Tile::Wall; cols]; rows];
+    for cell in floor.iter() {
+        tiles[cell / cols][cell % cols] = Tile::Floor;
+    }
+    for (cell, color) in goals.iter() {
+        tiles[cell / cols][cell % cols] = Tile::BoxSpot(*color);
+    }
+    for (cell, color) in boxes.iter() {
+        tiles[cell / cols][cell % cols] = Tile::Box(*color);
+    }
+    tiles[player / cols][player % cols] = Tile::Player;
+
+    let level = Level::new(tiles);
+    let solution = solve(&level, MAX_SOLVER_STATES).ok().flatten()?;
+    if solution.is_empty() {
+        return None;
+    }
+
+    Some(GeneratedLevel { level, solution })
+}
+
+// The floor cells of a room surrounded by walls with some walls dotted around
+// inside. Returns None if the walls split the room in two.
+fn room<R: Rng>(rows: usize, cols: usize, rng: &mut R) -> Option<Vec<usize>> {
+    if rows < 3 || cols < 3 {
+        return None;
+    }
+
+    let inside: Vec<usize> = (1..rows - 1)
+        .flat_map(|y| (1..cols - 1).map(move |x| y * cols + x))
+        .collect();
+    let floor: Vec<usize> = inside
+        .iter()
+        .copied()
+        .filter(|_| !rng.gen_bool(WALL_DENSITY))
+        .collect();
+
+    // Check every floor cell can be reached from the first one
+    let mut reached = vec![false; rows * cols];
+    let mut queue = VecDeque::from(vec![*floor.first()?]);
+    reached[floor[0]] = true;
+    let mut count = 0;
+    while let Some(cell) = queue.pop_front() {
+        count += 1;
+        for next in [cell - 1, cell + 1, cell - cols, cell + cols] {
+            if !reached[next] && floor.contains(&next) {
+                reached[next] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    if count == floor.len() {
+        Some(floor)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,64 @@
+use ggez::event::KeyCode;
+use specs::{RunNow, System, World, WorldExt};
+
+use crate::components::{register_components, Coord};
+use crate::map::{load_map, Level};
+use crate::resources::{register_resources, Gameplay, GameplayState, InputQueue};
+use crate::systems::{DoorSystem, GameplayStateSystem, InputSystem, StatsSystem};
+
+// A level played with the same world and systems as the window, minus
+// rendering and sound. Each key pressed is played straight away.
+pub struct Headless {
+    pub world: World,
+    input: InputSystem,
+    doors: DoorSystem,
+    stats: StatsSystem,
+    gameplay: GameplayStateSystem,
+}
+
+impl Headless {
+    pub fn new(level: &Level) -> Self {
+        let mut world = World::new();
+        register_components(&mut world);
+        register_resources(&mut world);
+        let (rows, cols) = load_map(&mut world, level);
+
+        let mut stats = StatsSystem::default();
+        System::setup(&mut stats, &mut world);
+
+        Self {
+            world,
+            input: InputSystem { cols, rows },
+            doors: DoorSystem {},
+            stats,
+            gameplay: GameplayStateSystem {},
+        }
+    }
+
+    pub fn rows(&self) -> Coord {
+        self.input.rows
+    }
+
+    pub fn cols(&self) -> Coord {
+        self.input.cols
+    }
+
+    pub fn press(&mut self, keycode: KeyCode) {
+        self.world
+            .write_resource::<InputQueue>()
+            .keys_pressed
+            .push_back(keycode);
+        self.input.run_now(&self.world);
+        self.doors.run_now(&self.world);
+        self.stats.run_now(&self.world);
+        self.gameplay.run_now(&self.world);
+        self.world.maintain();
+    }
+
+    pub fn is_won(&self) -> bool {
+        matches!(
+            self.world.read_resource::<Gameplay>().state,
+            GameplayState::Won
+        )
+    }
+}
@@ -0,0 +1,25 @@
+// The game rules, levels and tools, shared by the game and anything driving
+// it without a window
+pub mod audio;
+pub mod campaign;
+pub mod canonical;
+pub mod cli;
+pub mod components;
+pub mod constants;
+pub mod entities;
+pub mod env;
+pub mod events;
+pub mod generator;
+pub mod headless;
+pub mod map;
+pub mod palette;
+pub mod remote;
+pub mod replay;
+pub mod resources;
+pub mod scenes;
+pub mod screenshot;
+pub mod solver;
+pub mod svg;
+pub mod systems;
+pub mod tui;
+pub mod ui;
@@ -1,35 +1,110 @@
-use crate::audio::initialize_sounds;
 use ggez::{
     conf,
-    event::{self, KeyCode, KeyMods},
+    event::{self, Button, GamepadId, KeyCode, KeyMods, MouseButton},
+    graphics::{self, Rect},
     timer, Context, GameResult,
 };
-use specs::{Dispatcher, DispatcherBuilder, RunNow, World, WorldExt};
-use std::path;
-
-mod audio;
-mod components;
-mod constants;
-mod entities;
-mod events;
-mod map;
-mod resources;
-mod systems;
-
-use crate::components::*;
-use crate::constants::{MULTIPLIER, TEXT_PADDING, TEXT_SIZE, TILE_WIDTH};
-use crate::map::*;
-use crate::resources::*;
-use crate::systems::*;
-
-// This struct will hold all our game state
-// For now there is nothing to be held, but we'll add
-// things shortly.
+use serde_json::Value;
+use std::{env, path};
+
+use rust_sokoban::campaign::Campaign;
+use rust_sokoban::cli;
+use rust_sokoban::constants::{MULTIPLIER, TEXT_PADDING, TEXT_SIZE, TILE_WIDTH};
+use rust_sokoban::map::Level;
+use rust_sokoban::remote::{self, error_message, Command, RemoteServer};
+use rust_sokoban::scenes::{GameplayScene, MenuScene, Scene, Transition};
+use rust_sokoban::ui::unscale;
+
+// The smallest window, in tiles, so the menus always fit
+const MIN_TILES: usize = 8;
+
+// This struct will hold all our game state. Each screen is a scene, the one
+// on top of the stack is the one being played.
 struct Game {
-    world: World,
-    dispatcher: Dispatcher<'static, 'static>,
-    rows: u8,
-    cols: u8,
+    scenes: Vec<Box<dyn Scene>>,
+    campaign: Campaign,
+    remote: Option<RemoteServer>,
+}
+
+impl Game {
+    fn apply(&mut self, context: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => (),
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop(count) => {
+                let remaining = self.scenes.len().saturating_sub(count);
+                self.scenes.truncate(remaining);
+            }
+            Transition::Replace(count, scene) => {
+                let remaining = self.scenes.len().saturating_sub(count);
+                self.scenes.truncate(remaining);
+                self.scenes.push(scene);
+            }
+            Transition::Quit => event::quit(context),
+        }
+
+        if self.scenes.is_empty() {
+            event::quit(context);
+        }
+    }
+
+    fn run_remote_commands(&mut self, context: &mut Context) {
+        let commands = match &self.remote {
+            Some(remote) => remote.poll(),
+            None => return,
+        };
+
+        for (client, command) in commands {
+            if let (Some(reply), Some(remote)) =
+                (self.run_remote_command(context, command), &self.remote)
+            {
+                remote.send(client, &reply);
+            }
+        }
+    }
+
+    // Returns the reply to send back, if there is one
+    fn run_remote_command(&mut self, context: &mut Context, command: Command) -> Option<Value> {
+        match command {
+            Command::QueryState => Some(
+                self.scenes
+                    .last()
+                    .and_then(|scene| scene.remote_state())
+                    .unwrap_or_else(|| error_message(&"no level is being played")),
+            ),
+            Command::LoadLevel { index, map } => {
+                let scene = match (index, map) {
+                    (Some(index), None) if index < self.campaign.levels.len() => {
+                        GameplayScene::from_campaign(context, &self.campaign, index)
+                    }
+                    (None, Some(map)) => match Level::try_parse(&map) {
+                        Ok(level) => GameplayScene::new(context, level, None),
+                        Err(error) => return Some(error_message(&error)),
+                    },
+                    _ => return Some(error_message(&"expected a level index or a map")),
+                };
+
+                // Keep the main menu under the level to go back to
+                let count = self.scenes.len().saturating_sub(1);
+                self.apply(context, Transition::Replace(count, Box::new(scene)));
+                None
+            }
+            command => {
+                let campaign = &mut self.campaign;
+                let transition = match self.scenes.last_mut() {
+                    Some(scene) => scene.remote_command(context, campaign, &command),
+                    None => Err("no level is being played".to_string()),
+                };
+                match transition {
+                    Ok(transition) => {
+                        self.apply(context, transition);
+                        None
+                    }
+                    Err(error) => Some(error_message(&error)),
+                }
+            }
+        }
+    }
 }
 
 impl event::EventHandler for Game {
@@ -41,14 +116,27 @@ impl event::EventHandler for Game {
             timer::delta(ctx)
         );
 
-        self.dispatcher.dispatch(&mut self.world);
-        {
-            let mut es = EventSystem { context: ctx };
-            es.run_now(&self.world);
+        self.run_remote_commands(ctx);
+
+        if let Some(scene) = self.scenes.last_mut() {
+            let transition = scene.update(ctx, &mut self.campaign);
+
+            // Tell remote clients what happened, with the state after it
+            let events = scene.remote_events();
+            match &self.remote {
+                Some(remote) if !events.is_empty() => {
+                    for event in events.iter() {
+                        remote.broadcast(event);
+                    }
+                    if let Some(state) = scene.remote_state() {
+                        remote.broadcast(&state);
+                    }
+                }
+                _ => (),
+            }
+
+            self.apply(ctx, transition);
         }
-        self.world.maintain();
-        let mut time = self.world.write_resource::<Time>();
-        time.delta += timer::delta(ctx);
 
         Ok(())
     }
@@ -61,83 +149,112 @@ impl event::EventHandler for Game {
             timer::fps(ctx),
             timer::delta(ctx)
         );
-        {
-            let mut rs = RenderingSystem {
-                context: ctx,
-                cols: self.cols,
-                rows: self.rows,
-            };
-            rs.run_now(&self.world);
+
+        // Clearing the screen (this gives us the background colour)
+        graphics::clear(ctx, graphics::Color::new(0.95, 0.95, 0.95, 1.0));
+
+        // Draw from the topmost scene that isn't an overlay upwards
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in self.scenes[first..].iter_mut() {
+            scene.draw(ctx, &self.campaign)?;
         }
-        Ok(())
+
+        // Finally, present the context, this will actually display everything
+        // on the screen.
+        graphics::present(ctx)
     }
 
     fn key_down_event(
         &mut self,
-        _context: &mut Context,
+        context: &mut Context,
         keycode: KeyCode,
         _keymod: KeyMods,
         _repeat: bool,
     ) {
         println!("Key pressed: {:?}", keycode);
 
-        let mut input_queue = self.world.write_resource::<InputQueue>();
-        input_queue.keys_pressed.push(keycode);
+        if let Some(scene) = self.scenes.last_mut() {
+            let transition = scene.key_down(context, &mut self.campaign, keycode);
+            self.apply(context, transition);
+        }
+    }
+
+    // A gamepad plays like the keys it stands in for. Its d-pad is the
+    // arrows, which are player two's keys in co-op.
+    fn gamepad_button_down_event(&mut self, context: &mut Context, button: Button, _id: GamepadId) {
+        let keycode = match button {
+            Button::DPadLeft => KeyCode::Left,
+            Button::DPadUp => KeyCode::Up,
+            Button::DPadRight => KeyCode::Right,
+            Button::DPadDown => KeyCode::Down,
+            Button::South => KeyCode::Return,
+            Button::West => KeyCode::U,
+            Button::North => KeyCode::Tab,
+            Button::Start => KeyCode::Escape,
+            _ => return,
+        };
+        self.key_down_event(context, keycode, KeyMods::NONE, false);
     }
-}
 
-// Initialize the level
-pub fn initialize_level(world: &mut World) -> (u8, u8) {
-    const MAP: &str = "
-    N N W W W W W W
-    W W W . . . . W
-    W . . . BR BB . W
-    W . . . . . . W
-    W . P . . . . W
-    W . . . . . . W
-    W . . SR SB . . W
-    W . . . . . . W
-    W W W W W W W W
-    ";
-
-    return load_map(world, MAP.to_string());
+    fn mouse_button_down_event(
+        &mut self,
+        context: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        if button != MouseButton::Left {
+            return;
+        }
+
+        if let Some(scene) = self.scenes.last_mut() {
+            let (x, y) = unscale(x, y);
+            let transition = scene.mouse_down(context, &mut self.campaign, x, y);
+            self.apply(context, transition);
+        }
+    }
 }
 
 pub fn main() -> GameResult {
-    let mut world = World::new();
-    register_components(&mut world);
-    register_resources(&mut world);
-    let (rows, cols) = initialize_level(&mut world);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if cli::run_command(&args) {
+        return Ok(());
+    }
+
+    // --remote [address] lets other programs drive the game
+    let remote = args.iter().position(|arg| arg == "--remote").map(|index| {
+        let address = args
+            .get(index + 1)
+            .map_or(remote::DEFAULT_ADDRESS, String::as_str);
+        println!("Listening for remote clients on {}", address);
+        RemoteServer::start(address).expect("expected to listen for remote clients")
+    });
 
     // Create a game context and event loop
     let context_builder = ggez::ContextBuilder::new("rust_sokoban", "sokoban")
         .window_setup(conf::WindowSetup::default().title("Rust Sokoban!"))
-        .window_mode(conf::WindowMode::default().dimensions(
-            cols as f32 * TILE_WIDTH * MULTIPLIER,
-            ((rows as f32 * TILE_WIDTH) + (TEXT_SIZE + TEXT_PADDING) * 2.0) * MULTIPLIER,
-        ))
         .add_resource_path(path::PathBuf::from("./resources"));
 
     let (mut context, event_loop) = context_builder.build()?;
 
-    let dispatcher = DispatcherBuilder::new()
-        .with(
-            InputSystem {
-                cols: cols,
-                rows: rows,
-            },
-            "input",
-            &[],
-        )
-        .with(GameplayStateSystem {}, "gameplay", &[])
-        .build();
-    initialize_sounds(&mut world, &mut context);
+    // Size the window so the largest level fits
+    let campaign = Campaign::load(&mut context);
+    let (rows, cols) = campaign.max_size();
+    let width = cols.max(MIN_TILES) as f32 * TILE_WIDTH * MULTIPLIER;
+    let height =
+        ((rows.max(MIN_TILES) as f32 * TILE_WIDTH) + (TEXT_SIZE + TEXT_PADDING) * 2.0) * MULTIPLIER;
+    graphics::set_drawable_size(&mut context, width, height)?;
+    graphics::set_screen_coordinates(&mut context, Rect::new(0.0, 0.0, width, height))?;
+
     // Create the game state
     let game = Game {
-        world,
-        dispatcher,
-        rows,
-        cols,
+        scenes: vec![Box::new(MenuScene::new())],
+        campaign,
+        remote,
     };
     // Run the main event loop
     event::run(context, event_loop, game)
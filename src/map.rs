@@ -1,56 +1,598 @@
+use crate::canonical::canonical_level;
 use crate::components::Position;
 use crate::components::*;
 use crate::entities::*;
-use specs::World;
+use crate::palette::Palette;
+use specs::{Join, World, WorldExt};
+use std::collections::{HashMap, HashSet};
 
-pub fn load_map(world: &mut World, map_string: String) -> (u8, u8) {
-    // read all lines
-    let rows: Vec<&str> = map_string.trim().split('\n').map(|x| x.trim()).collect();
-    let mut columns: Vec<&str> = vec![];
+// A single cell of a parsed map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Nothing,
+    Floor,
+    Wall,
+    Player,
+    Box(BoxColor),
+    BoxSpot(BoxColor),
+    // A box of the first color on a spot of the second
+    BoxOnSpot(BoxColor, BoxColor),
+    PlayerOnSpot(BoxColor),
+    // Teleporters are paired by the character after the `T`
+    Teleporter(char),
+    BoxOnTeleporter(BoxColor, char),
+    PlayerOnTeleporter(char),
+    Ice,
+    BoxOnIce(BoxColor),
+    PlayerOnIce,
+    Gate(Gate),
+    BoxOnGate(BoxColor, Gate),
+    PlayerOnGate(Gate),
+    // Filled pits are floors, nothing stands on an open one
+    Pit,
+    // Plates, switches and doors are linked by the character after the
+    // letter, switches that are on are written in lower case
+    Plate(char),
+    BoxOnPlate(BoxColor, char),
+    PlayerOnPlate(char),
+    Switch(Switch),
+    BoxOnSwitch(BoxColor, Switch),
+    PlayerOnSwitch(Switch),
+    Door(char),
+    BoxOnDoor(BoxColor, char),
+    PlayerOnDoor(char),
+}
+
+impl Tile {
+    pub fn parse_token(token: &str) -> Option<Self> {
+        let chars: Vec<char> = token.chars().collect();
+        match chars[..] {
+            ['N'] => Some(Tile::Nothing),
+            ['.'] => Some(Tile::Floor),
+            ['W'] => Some(Tile::Wall),
+            ['P'] => Some(Tile::Player),
+            ['B', color] => Some(Tile::Box(BoxColor(color))),
+            ['S', color] => Some(Tile::BoxSpot(BoxColor(color))),
+            ['B', color, 'S', spot] => Some(Tile::BoxOnSpot(BoxColor(color), BoxColor(spot))),
+            ['P', 'S', spot] => Some(Tile::PlayerOnSpot(BoxColor(spot))),
+            ['T', id] => Some(Tile::Teleporter(id)),
+            ['B', color, 'T', id] => Some(Tile::BoxOnTeleporter(BoxColor(color), id)),
+            ['P', 'T', id] => Some(Tile::PlayerOnTeleporter(id)),
+            ['I'] => Some(Tile::Ice),
+            ['B', color, 'I'] => Some(Tile::BoxOnIce(BoxColor(color))),
+            ['P', 'I'] => Some(Tile::PlayerOnIce),
+            ['G', ref gate @ ..] => Gate::parse(gate).map(Tile::Gate),
+            ['B', color, 'G', ref gate @ ..] => {
+                Gate::parse(gate).map(|gate| Tile::BoxOnGate(BoxColor(color), gate))
+            }
+            ['P', 'G', ref gate @ ..] => Gate::parse(gate).map(Tile::PlayerOnGate),
+            ['H'] => Some(Tile::Pit),
+            ['O', id] => Some(Tile::Plate(id)),
+            ['B', color, 'O', id] => Some(Tile::BoxOnPlate(BoxColor(color), id)),
+            ['P', 'O', id] => Some(Tile::PlayerOnPlate(id)),
+            [letter @ ('X' | 'x'), id] => Some(Tile::Switch(switch(letter, id))),
+            ['B', color, letter @ ('X' | 'x'), id] => {
+                Some(Tile::BoxOnSwitch(BoxColor(color), switch(letter, id)))
+            }
+            ['P', letter @ ('X' | 'x'), id] => Some(Tile::PlayerOnSwitch(switch(letter, id))),
+            ['D', id] => Some(Tile::Door(id)),
+            ['B', color, 'D', id] => Some(Tile::BoxOnDoor(BoxColor(color), id)),
+            ['P', 'D', id] => Some(Tile::PlayerOnDoor(id)),
+            _ => None,
+        }
+    }
+
+    // The color of the box on this tile, if there is one
+    pub fn box_color(&self) -> Option<BoxColor> {
+        match *self {
+            Tile::Box(color)
+            | Tile::BoxOnSpot(color, _)
+            | Tile::BoxOnTeleporter(color, _)
+            | Tile::BoxOnIce(color)
+            | Tile::BoxOnGate(color, _)
+            | Tile::BoxOnPlate(color, _)
+            | Tile::BoxOnSwitch(color, _)
+            | Tile::BoxOnDoor(color, _) => Some(color),
+            _ => None,
+        }
+    }
+
+    // Whether the player starts on this tile
+    pub fn has_player(&self) -> bool {
+        matches!(
+            self,
+            Tile::Player
+                | Tile::PlayerOnSpot(_)
+                | Tile::PlayerOnTeleporter(_)
+                | Tile::PlayerOnIce
+                | Tile::PlayerOnGate(_)
+                | Tile::PlayerOnPlate(_)
+                | Tile::PlayerOnSwitch(_)
+                | Tile::PlayerOnDoor(_)
+        )
+    }
+
+    // Whether the tile is ice, with or without something on it
+    pub fn is_ice(&self) -> bool {
+        matches!(self, Tile::Ice | Tile::BoxOnIce(_) | Tile::PlayerOnIce)
+    }
+
+    // The one-way gate under whatever is on this tile
+    pub fn gate(&self) -> Option<Gate> {
+        match *self {
+            Tile::Gate(gate) | Tile::BoxOnGate(_, gate) | Tile::PlayerOnGate(gate) => Some(gate),
+            _ => None,
+        }
+    }
+
+    // The id of the pressure plate under whatever is on this tile
+    pub fn plate(&self) -> Option<char> {
+        match *self {
+            Tile::Plate(id) | Tile::BoxOnPlate(_, id) | Tile::PlayerOnPlate(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    // The switch under whatever is on this tile
+    pub fn switch(&self) -> Option<Switch> {
+        match *self {
+            Tile::Switch(switch) | Tile::BoxOnSwitch(_, switch) | Tile::PlayerOnSwitch(switch) => {
+                Some(switch)
+            }
+            _ => None,
+        }
+    }
+
+    // The id of the door under whatever is on this tile
+    pub fn door(&self) -> Option<char> {
+        match *self {
+            Tile::Door(id) | Tile::BoxOnDoor(_, id) | Tile::PlayerOnDoor(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    // The id of the teleporter under whatever is on this tile
+    pub fn teleporter(&self) -> Option<char> {
+        match *self {
+            Tile::Teleporter(id) | Tile::BoxOnTeleporter(_, id) | Tile::PlayerOnTeleporter(id) => {
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn token(&self) -> String {
+        match self {
+            Tile::Nothing => "N".to_string(),
+            Tile::Floor => ".".to_string(),
+            Tile::Wall => "W".to_string(),
+            Tile::Player => "P".to_string(),
+            Tile::Box(color) => format!("B{}", color),
+            Tile::BoxSpot(color) => format!("S{}", color),
+            Tile::BoxOnSpot(color, spot) => format!("B{}S{}", color, spot),
+            Tile::PlayerOnSpot(spot) => format!("PS{}", spot),
+            Tile::Teleporter(id) => format!("T{}", id),
+            Tile::BoxOnTeleporter(color, id) => format!("B{}T{}", color, id),
+            Tile::PlayerOnTeleporter(id) => format!("PT{}", id),
+            Tile::Ice => "I".to_string(),
+            Tile::BoxOnIce(color) => format!("B{}I", color),
+            Tile::PlayerOnIce => "PI".to_string(),
+            Tile::Gate(gate) => gate.token(),
+            Tile::BoxOnGate(color, gate) => format!("B{}{}", color, gate.token()),
+            Tile::PlayerOnGate(gate) => format!("P{}", gate.token()),
+            Tile::Pit => "H".to_string(),
+            Tile::Plate(id) => format!("O{}", id),
+            Tile::BoxOnPlate(color, id) => format!("B{}O{}", color, id),
+            Tile::PlayerOnPlate(id) => format!("PO{}", id),
+            Tile::Switch(switch) => switch_token(switch),
+            Tile::BoxOnSwitch(color, switch) => format!("B{}{}", color, switch_token(switch)),
+            Tile::PlayerOnSwitch(switch) => format!("P{}", switch_token(switch)),
+            Tile::Door(id) => format!("D{}", id),
+            Tile::BoxOnDoor(color, id) => format!("B{}D{}", color, id),
+            Tile::PlayerOnDoor(id) => format!("PD{}", id),
+        }
+    }
+}
+
+fn switch(letter: char, id: char) -> Switch {
+    Switch {
+        id,
+        on: letter == 'x',
+    }
+}
+
+// A row in the usual Sokoban text format, one character per cell, as used
+// by level collections: `#` wall, `@` player, `$` box, `.` goal, `*` box on
+// a goal, `+` player on a goal and space, `-` or `_` floor. Its boxes and
+// goals are neutral, so they read the same as `B*`, `S*`, `B*S*` and `PS*`.
+fn parse_xsb_row(row: &str) -> Result<Vec<Tile>, String> {
+    row.trim_end()
+        .chars()
+        .map(|c| match c {
+            '#' => Ok(Tile::Wall),
+            ' ' | '-' | '_' => Ok(Tile::Floor),
+            '@' => Ok(Tile::Player),
+            '+' => Ok(Tile::PlayerOnSpot(BoxColor::ANY)),
+            '$' => Ok(Tile::Box(BoxColor::ANY)),
+            '.' => Ok(Tile::BoxSpot(BoxColor::ANY)),
+            '*' => Ok(Tile::BoxOnSpot(BoxColor::ANY, BoxColor::ANY)),
+            c => Err(format!("unrecognized map item {}", c)),
+        })
+        .collect()
+}
+
+fn switch_token(switch: &Switch) -> String {
+    let letter = if switch.on { 'x' } else { 'X' };
+    format!("{}{}", letter, switch.id)
+}
+
+// A map parsed into rows of tiles, before any entities are created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Level {
+    pub tiles: Vec<Vec<Tile>>,
+    pub palette: Palette,
+}
+
+impl Level {
+    pub fn new(tiles: Vec<Vec<Tile>>) -> Self {
+        Self {
+            tiles,
+            palette: Palette::default(),
+        }
+    }
+
+    pub fn parse(map_string: &str) -> Self {
+        Self::try_parse(map_string).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Like `parse`, for maps that come from somewhere they might be wrong
+    pub fn try_parse(map_string: &str) -> Result<Self, String> {
+        let mut level = Self::new(vec![]);
+        let is_xsb = map_string
+            .lines()
+            .any(|row| !row.trim_start().starts_with("color") && row.contains('#'));
+
+        // read all lines, any amount of whitespace separates the tokens.
+        // Lines starting with `color` declare the level's box colors. Leading
+        // spaces are cells in the Sokoban format, so only blank lines go.
+        let rows = if is_xsb {
+            map_string.trim_matches(|c| c == '\n' || c == '\r')
+        } else {
+            map_string.trim()
+        };
+        for row in rows.lines() {
+            if row.trim_start().starts_with("color") {
+                let color = Palette::parse_line(row)
+                    .ok_or_else(|| format!("unrecognized color {}", row.trim()))?;
+                level.palette.declared.push(color);
+                continue;
+            }
+            if is_xsb {
+                level.tiles.push(parse_xsb_row(row)?);
+                continue;
+            }
+
+            let tiles = row
+                .split_whitespace()
+                .map(|token| {
+                    Tile::parse_token(token)
+                        .ok_or_else(|| format!("unrecognized map item {}", token))
+                })
+                .collect::<Result<_, _>>()?;
+            level.tiles.push(tiles);
+        }
+
+        // The floor outside the walls isn't part of the level
+        if is_xsb {
+            level.tiles = canonical_level(&level).tiles;
+        }
+        Ok(level)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.tiles.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.tiles
+            .iter()
+            .flatten()
+            .filter(|tile| tile.has_player())
+            .count()
+    }
 
-    for (y, row) in rows.iter().enumerate() {
-        columns = row.split(' ').collect();
+    // The ids of doors held open at the start, by plates with something on
+    // them and switches that are on
+    pub fn held_open(&self) -> HashSet<char> {
+        self.tiles
+            .iter()
+            .flatten()
+            .filter_map(|tile| match *tile {
+                Tile::BoxOnPlate(_, id) | Tile::PlayerOnPlate(id) => Some(id),
+                _ => tile
+                    .switch()
+                    .filter(|switch| switch.on)
+                    .map(|switch| switch.id),
+            })
+            .collect()
+    }
+
+    // The cell each teleporter leads to, by (x, y). The first two teleporters
+    // with the same id, in reading order, are a pair.
+    pub fn teleporter_exits(&self) -> HashMap<(usize, usize), (usize, usize)> {
+        let mut by_id: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
+        for (y, row) in self.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if let Some(id) = tile.teleporter() {
+                    by_id.entry(id).or_default().push((x, y));
+                }
+            }
+        }
+
+        let mut exits = HashMap::new();
+        for cells in by_id.values() {
+            if let [a, b, ..] = cells[..] {
+                exits.insert(a, b);
+                exits.insert(b, a);
+            }
+        }
+        exits
+    }
+
+    pub fn to_map_string(&self) -> String {
+        let rows = self.tiles.iter().map(|row| {
+            row.iter()
+                .map(|tile| tile.token())
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+        self.palette
+            .to_lines()
+            .into_iter()
+            .chain(rows)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
 
-        for (x, column) in columns.iter().enumerate() {
+pub fn load_map(world: &mut World, level: &Level) -> (Coord, Coord) {
+    // Kept so the level can be read back with its colors
+    world.insert(level.palette.clone());
+    let exits = level.teleporter_exits();
+    let held_open = level.held_open();
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
             // Create the position at which to create something on the map
             let position = Position {
-                x: x as u8,
-                y: y as u8,
+                x: x as Coord,
+                y: y as Coord,
                 z: 0, // we will get the z from the factory functions
             };
 
             // Figure out what object we should create
-            match *column {
-                "." => create_floor(world, position),
-                "W" => {
+            match *tile {
+                Tile::Nothing => (),
+                Tile::Floor => create_floor(world, position),
+                Tile::Wall => {
                     create_floor(world, position);
                     create_wall(world, position);
                 }
-                "P" => {
+                Tile::Player => {
+                    create_floor(world, position);
+                    create_player(world, position);
+                }
+                Tile::Box(color) => {
                     create_floor(world, position);
+                    create_box(world, position, &level.palette.get(color));
+                }
+                Tile::BoxSpot(color) => {
+                    create_floor(world, position);
+                    create_box_spot(world, position, &level.palette.get(color));
+                }
+                Tile::BoxOnSpot(color, spot) => {
+                    create_floor(world, position);
+                    create_box_spot(world, position, &level.palette.get(spot));
+                    create_box(world, position, &level.palette.get(color));
+                }
+                Tile::PlayerOnSpot(spot) => {
+                    create_floor(world, position);
+                    create_box_spot(world, position, &level.palette.get(spot));
+                    create_player(world, position);
+                }
+                Tile::Teleporter(_) | Tile::BoxOnTeleporter(_, _) | Tile::PlayerOnTeleporter(_) => {
+                    let id = tile.teleporter().expect("expected a teleporter");
+                    let exit = exits.get(&(x, y)).map(|&(x, y)| (x as Coord, y as Coord));
+                    create_floor(world, position);
+                    create_teleporter(world, position, id, exit);
+                    match *tile {
+                        Tile::BoxOnTeleporter(color, _) => {
+                            create_box(world, position, &level.palette.get(color))
+                        }
+                        Tile::PlayerOnTeleporter(_) => create_player(world, position),
+                        _ => (),
+                    }
+                }
+                Tile::Ice => {
+                    create_floor(world, position);
+                    create_ice(world, position);
+                }
+                Tile::BoxOnIce(color) => {
+                    create_floor(world, position);
+                    create_ice(world, position);
+                    create_box(world, position, &level.palette.get(color));
+                }
+                Tile::PlayerOnIce => {
+                    create_floor(world, position);
+                    create_ice(world, position);
                     create_player(world, position);
                 }
-                "BB" => {
+                Tile::Gate(gate) => {
                     create_floor(world, position);
-                    create_box(world, position, BoxColor::Blue);
+                    create_gate(world, position, gate);
                 }
-                "BR" => {
+                Tile::BoxOnGate(color, gate) => {
                     create_floor(world, position);
-                    create_box(world, position, BoxColor::Red);
+                    create_gate(world, position, gate);
+                    create_box(world, position, &level.palette.get(color));
                 }
-                "SB" => {
+                Tile::PlayerOnGate(gate) => {
                     create_floor(world, position);
-                    create_box_spot(world, position, BoxColor::Blue);
+                    create_gate(world, position, gate);
+                    create_player(world, position);
                 }
-                "SR" => {
+                Tile::Pit => {
                     create_floor(world, position);
-                    create_box_spot(world, position, BoxColor::Red);
+                    create_pit(world, position);
+                }
+                Tile::Plate(id) | Tile::BoxOnPlate(_, id) | Tile::PlayerOnPlate(id) => {
+                    create_floor(world, position);
+                    create_plate(world, position, id);
+                    stand_on(world, level, position, tile);
+                }
+                Tile::Switch(switch)
+                | Tile::BoxOnSwitch(_, switch)
+                | Tile::PlayerOnSwitch(switch) => {
+                    create_floor(world, position);
+                    create_switch(world, position, switch);
+                    stand_on(world, level, position, tile);
+                }
+                Tile::Door(id) | Tile::BoxOnDoor(_, id) | Tile::PlayerOnDoor(id) => {
+                    let occupied = *tile != Tile::Door(id);
+                    create_floor(world, position);
+                    create_door(world, position, id, occupied || held_open.contains(&id));
+                    stand_on(world, level, position, tile);
                 }
-                "N" => (),
-                c => panic!("unrecognized map item {}", c),
             }
         }
     }
 
-    return (rows.len() as u8, columns.len() as u8);
+    // The first player in reading order is the one that moves to begin with
+    let first = {
+        let entities = world.entities();
+        let positions = world.read_storage::<Position>();
+        let players = world.read_storage::<Player>();
+        (&entities, &positions, &players)
+            .join()
+            .min_by_key(|(_, position, _)| (position.y, position.x))
+            .map(|(entity, _, _)| entity)
+    };
+    if let Some(first) = first {
+        world
+            .write_storage::<Active>()
+            .insert(first, Active)
+            .expect("expected to mark the player active");
+    }
+
+    (level.rows() as Coord, level.cols() as Coord)
+}
+
+// Create the box or player on a tile, if there is one
+fn stand_on(world: &mut World, level: &Level, position: Position, tile: &Tile) {
+    if let Some(color) = tile.box_color() {
+        create_box(world, position, &level.palette.get(color));
+    }
+    if tile.has_player() {
+        create_player(world, position);
+    }
+}
+
+// The level as it is being played, read back from the world with the palette
+// it was loaded with. Every entity stands on a floor, so cells with nothing but
+// a floor are floors.
+pub fn snapshot(world: &World) -> Level {
+    let positions = world.read_storage::<Position>();
+    let walls = world.read_storage::<Wall>();
+    let players = world.read_storage::<Player>();
+    let boxes = world.read_storage::<Box>();
+    let box_spots = world.read_storage::<BoxSpot>();
+    let teleporters = world.read_storage::<Teleporter>();
+    let ices = world.read_storage::<Ice>();
+    let gates = world.read_storage::<Gate>();
+    let pits = world.read_storage::<Pit>();
+    let plates = world.read_storage::<Plate>();
+    let switches = world.read_storage::<Switch>();
+    let doors = world.read_storage::<Door>();
+
+    let rows = (&positions)
+        .join()
+        .map(|p| p.y as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let cols = (&positions)
+        .join()
+        .map(|p| p.x as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let mut tiles = vec![vec![Tile::Nothing; cols]; rows];
+    for position in (&positions).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Floor;
+    }
+
+    // Ice, gates, spots, teleporters and the rest go down first so boxes and
+    // the player can stand on them. Filled pits stay floors.
+    for (position, _wall) in (&positions, &walls).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Wall;
+    }
+    for (position, pit) in (&positions, &pits).join() {
+        if !pit.filled {
+            tiles[position.y as usize][position.x as usize] = Tile::Pit;
+        }
+    }
+    for (position, _ice) in (&positions, &ices).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Ice;
+    }
+    for (position, gate) in (&positions, &gates).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Gate(*gate);
+    }
+    for (position, box_spot) in (&positions, &box_spots).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::BoxSpot(box_spot.color);
+    }
+    for (position, teleporter) in (&positions, &teleporters).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Teleporter(teleporter.id);
+    }
+    for (position, plate) in (&positions, &plates).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Plate(plate.id);
+    }
+    for (position, switch) in (&positions, &switches).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Switch(*switch);
+    }
+    for (position, door) in (&positions, &doors).join() {
+        tiles[position.y as usize][position.x as usize] = Tile::Door(door.id);
+    }
+    for (position, the_box) in (&positions, &boxes).join() {
+        let tile = &mut tiles[position.y as usize][position.x as usize];
+        *tile = match *tile {
+            Tile::Plate(id) => Tile::BoxOnPlate(the_box.color, id),
+            Tile::Switch(switch) => Tile::BoxOnSwitch(the_box.color, switch),
+            Tile::Door(id) => Tile::BoxOnDoor(the_box.color, id),
+            Tile::BoxSpot(spot) => Tile::BoxOnSpot(the_box.color, spot),
+            Tile::Teleporter(id) => Tile::BoxOnTeleporter(the_box.color, id),
+            Tile::Ice => Tile::BoxOnIce(the_box.color),
+            Tile::Gate(gate) => Tile::BoxOnGate(the_box.color, gate),
+            _ => Tile::Box(the_box.color),
+        };
+    }
+    for (position, _player) in (&positions, &players).join() {
+        let tile = &mut tiles[position.y as usize][position.x as usize];
+        *tile = match *tile {
+            Tile::Plate(id) => Tile::PlayerOnPlate(id),
+            Tile::Switch(switch) => Tile::PlayerOnSwitch(switch),
+            Tile::Door(id) => Tile::PlayerOnDoor(id),
+            Tile::BoxSpot(spot) => Tile::PlayerOnSpot(spot),
+            Tile::Teleporter(id) => Tile::PlayerOnTeleporter(id),
+            Tile::Ice => Tile::PlayerOnIce,
+            Tile::Gate(gate) => Tile::PlayerOnGate(gate),
+            _ => Tile::Player,
+        };
+    }
+
+    Level {
+        tiles,
+        palette: (*world.read_resource::<Palette>()).clone(),
+    }
 }
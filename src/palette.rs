@@ -0,0 +1,101 @@
+use crate::components::BoxColor;
+
+// The name of a box color, used to find its images, and the tint used when
+// there are no images for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorDef {
+    pub color: BoxColor,
+    pub name: String,
+    pub rgb: [u8; 3],
+}
+
+// The colors every level can use without declaring them
+const THEME: [(char, &str, [u8; 3]); 9] = [
+    ('*', "any", [255, 255, 255]),
+    ('R', "red", [215, 70, 70]),
+    ('B', "blue", [70, 110, 215]),
+    ('G', "green", [70, 180, 90]),
+    ('Y', "yellow", [235, 200, 50]),
+    ('O', "orange", [240, 140, 40]),
+    ('P', "purple", [155, 85, 205]),
+    ('C', "cyan", [60, 195, 210]),
+    ('K', "pink", [240, 130, 180]),
+];
+
+// Colors declared by a level with `color <key> <name> <rrggbb>` lines, on top
+// of the theme
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Palette {
+    pub declared: Vec<ColorDef>,
+}
+
+impl Palette {
+    pub fn get(&self, color: BoxColor) -> ColorDef {
+        if let Some(def) = self.declared.iter().find(|def| def.color == color) {
+            return def.clone();
+        }
+
+        match THEME.iter().find(|(key, _, _)| *key == color.0) {
+            Some((_, name, rgb)) => ColorDef {
+                color,
+                name: name.to_string(),
+                rgb: *rgb,
+            },
+            // Make up a tint for colors nobody named, spread around by the key
+            None => ColorDef {
+                color,
+                name: color.0.to_lowercase().to_string(),
+                rgb: [
+                    (color.0 as u32 * 73 % 200 + 55) as u8,
+                    (color.0 as u32 * 151 % 200 + 55) as u8,
+                    (color.0 as u32 * 37 % 200 + 55) as u8,
+                ],
+            },
+        }
+    }
+
+    // The colors that have a name, declared ones first
+    pub fn colors(&self) -> Vec<BoxColor> {
+        let mut colors: Vec<BoxColor> = self.declared.iter().map(|def| def.color).collect();
+        for (key, _, _) in THEME.iter() {
+            if !colors.contains(&BoxColor(*key)) {
+                colors.push(BoxColor(*key));
+            }
+        }
+        colors
+    }
+
+    // Parse a `color <key> <name> <rrggbb>` line
+    pub fn parse_line(line: &str) -> Option<ColorDef> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (key, name, hex) = match parts[..] {
+            ["color", key, name, hex] => (key, name, hex),
+            _ => return None,
+        };
+
+        let mut chars = key.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(key), None) => key,
+            _ => return None,
+        };
+        let rgb = u32::from_str_radix(hex.trim_start_matches('#'), 16).ok()?;
+
+        Some(ColorDef {
+            color: BoxColor(key),
+            name: name.to_string(),
+            rgb: [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8],
+        })
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        self.declared
+            .iter()
+            .map(|def| {
+                format!(
+                    "color {} {} {:02x}{:02x}{:02x}",
+                    def.color, def.name, def.rgb[0], def.rgb[1], def.rgb[2]
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,233 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::components::Position;
+use crate::env::Action;
+use crate::events::*;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+// A client that stops reading is dropped rather than holding up the game
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// A command sent by a client as a line of JSON, like
+// `{"command": "move", "direction": "left"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    // Co-op players give their seat, 0 for player one
+    Move {
+        direction: Action,
+        seat: Option<usize>,
+    },
+    Undo,
+    // Hand control to the next player on levels with more than one
+    SwitchPlayer,
+    Restart,
+    // A level of the campaign by index, or a level in the map format
+    LoadLevel {
+        index: Option<usize>,
+        map: Option<String>,
+    },
+    QueryState,
+}
+
+pub type ClientId = usize;
+
+// Lines for each client are queued for its own writer thread, so a slow
+// client never holds up the game
+type Clients = Arc<Mutex<Vec<(ClientId, Sender<String>)>>>;
+
+// Lets tools on the same machine drive the game. Clients send commands as
+// JSON lines and get events, state and errors back as JSON lines.
+pub struct RemoteServer {
+    commands: Receiver<(ClientId, Command)>,
+    clients: Clients,
+    address: SocketAddr,
+}
+
+impl RemoteServer {
+    pub fn start(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let (sender, commands) = mpsc::channel();
+        let clients: Clients = Arc::new(Mutex::new(vec![]));
+
+        let accepted = clients.clone();
+        thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                match stream {
+                    Ok(stream) => accept(id, stream, &accepted, sender.clone()),
+                    Err(error) => eprintln!("Remote connection failed: {}", error),
+                }
+            }
+        });
+
+        Ok(Self {
+            commands,
+            clients,
+            address,
+        })
+    }
+
+    // Where clients connect, with the port picked if it was 0
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // Commands received since the last poll
+    pub fn poll(&self) -> Vec<(ClientId, Command)> {
+        self.commands.try_iter().collect()
+    }
+
+    pub fn send(&self, client: ClientId, message: &Value) {
+        write_message(&self.clients, |id| id == client, message);
+    }
+
+    pub fn broadcast(&self, message: &Value) {
+        write_message(&self.clients, |_| true, message);
+    }
+}
+
+// Read commands from a client on its own thread until it disconnects
+fn accept(id: ClientId, stream: TcpStream, clients: &Clients, sender: Sender<(ClientId, Command)>) {
+    let reader = match stream.try_clone() {
+        Ok(reader) => reader,
+        Err(error) => {
+            eprintln!("Remote connection failed: {}", error);
+            return;
+        }
+    };
+    stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok();
+    let (lines, queued) = mpsc::channel::<String>();
+    clients.lock().expect("expected clients").push((id, lines));
+
+    // A client that can't be written to is disconnected, which ends its
+    // reader too
+    thread::spawn(move || {
+        let mut stream = stream;
+        for line in queued {
+            if stream.write_all(line.as_bytes()).is_err() {
+                stream.shutdown(Shutdown::Both).ok();
+                break;
+            }
+        }
+    });
+
+    let clients = clients.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(command) => {
+                    if sender.send((id, command)).is_err() {
+                        break;
+                    }
+                }
+                Err(error) => write_message(&clients, |to| to == id, &error_message(&error)),
+            }
+        }
+
+        let mut clients = clients.lock().expect("expected clients");
+        clients.retain(|(other, _)| *other != id);
+    });
+}
+
+// Clients whose writer has stopped have gone away and are dropped
+fn write_message(clients: &Clients, to: impl Fn(ClientId) -> bool, message: &Value) {
+    let line = format!("{}\n", message);
+    let mut clients = clients.lock().expect("expected clients");
+    clients.retain(|(id, lines)| !to(*id) || lines.send(line.clone()).is_ok());
+}
+
+pub fn error_message(error: &dyn std::fmt::Display) -> Value {
+    json!({ "type": "error", "message": error.to_string() })
+}
+
+fn point(position: &Position) -> Value {
+    json!([position.x, position.y])
+}
+
+pub fn event_message(event: &Event) -> Value {
+    match event {
+        Event::LevelStarted => json!({ "type": "event", "event": "level_started" }),
+        Event::LevelCompleted => json!({ "type": "event", "event": "level_completed" }),
+        Event::PlayerHitObstacle => json!({ "type": "event", "event": "player_hit_obstacle" }),
+        Event::PlayerMoved(PlayerMoved { entity, from, to }) => json!({
+            "type": "event",
+            "event": "player_moved",
+            "entity": entity.id(),
+            "from": point(from),
+            "to": point(to),
+        }),
+        Event::EntityMoved(EntityMoved { entity, to }) => json!({
+            "type": "event",
+            "event": "entity_moved",
+            "entity": entity.id(),
+            "to": point(to),
+        }),
+        Event::BoxPushed(BoxPushed { entity, from, to }) => json!({
+            "type": "event",
+            "event": "box_pushed",
+            "entity": entity.id(),
+            "from": point(from),
+            "to": point(to),
+        }),
+        Event::BoxPlacedOnSpot(BoxPlacedOnSpot {
+            entity,
+            is_correct_spot,
+        }) => json!({
+            "type": "event",
+            "event": "box_placed_on_spot",
+            "entity": entity.id(),
+            "is_correct_spot": is_correct_spot,
+        }),
+        Event::BoxRemovedFromSpot(BoxRemovedFromSpot { entity }) => json!({
+            "type": "event",
+            "event": "box_removed_from_spot",
+            "entity": entity.id(),
+        }),
+        Event::Teleported(Teleported {
+            entity,
+            entrance,
+            exit,
+        }) => json!({
+            "type": "event",
+            "event": "teleported",
+            "entity": entity.id(),
+            "entrance": point(entrance),
+            "exit": point(exit),
+        }),
+        Event::BoxFellInPit(BoxFellInPit { entity, pit }) => json!({
+            "type": "event",
+            "event": "box_fell_in_pit",
+            "entity": entity.id(),
+            "pit": point(pit),
+        }),
+        Event::DoorToggled(DoorToggled { entity, open }) => json!({
+            "type": "event",
+            "event": "door_toggled",
+            "entity": entity.id(),
+            "open": open,
+        }),
+        Event::Undo(Undo { pushes, player }) => json!({
+            "type": "event",
+            "event": "undo",
+            "pushes": pushes,
+            "player": player.map(|player| player.id()),
+        }),
+    }
+}
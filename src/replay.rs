@@ -0,0 +1,68 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageError, ImageResult, RgbaImage};
+use specs::WorldExt;
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::env::{Action, RewardConfig, SokobanEnv};
+use crate::map::Level;
+use crate::resources::Time;
+use crate::screenshot::Screenshot;
+
+// The last frame stays up at least this long so the solved level can be seen
+// before the animation loops
+const LAST_FRAME_MS: u32 = 1000;
+
+// Plays a solution with the game's own systems and draws every step,
+// starting with the level as loaded. Animated images move on by `delay` each
+// step like they would in the window. Returns the frames and whether the
+// solution solved the level, or why it couldn't be played.
+pub fn replay(
+    level: &Level,
+    solution: &str,
+    screenshot: &mut Screenshot,
+    delay: Duration,
+) -> Result<(Vec<RgbaImage>, bool), String> {
+    let actions = solution
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| Action::from_lurd(c).ok_or(format!("Not a LURD move: {}", c)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let config = RewardConfig {
+        max_steps: None,
+        terminate_on_deadlock: false,
+        ..RewardConfig::default()
+    };
+    let mut env = SokobanEnv::new(config, vec![]);
+    env.reset(level)?;
+
+    let mut frames = vec![screenshot.render(env.world())];
+    for action in actions {
+        env.step(action);
+        env.world().write_resource::<Time>().delta += delay;
+        frames.push(screenshot.render(env.world()));
+    }
+
+    Ok((frames, env.info().solved))
+}
+
+// Save frames as a looping GIF, `delay` apart
+pub fn save_gif(frames: Vec<RgbaImage>, delay: Duration, path: &Path) -> ImageResult<()> {
+    let file = File::create(path).map_err(ImageError::IoError)?;
+    let mut encoder = GifEncoder::new(file);
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    let delay_ms = delay.as_millis() as u32;
+    let count = frames.len();
+    let frames = frames.into_iter().enumerate().map(|(index, buffer)| {
+        let ms = if index + 1 == count {
+            delay_ms.max(LAST_FRAME_MS)
+        } else {
+            delay_ms
+        };
+        Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(ms, 1))
+    });
+    encoder.encode_frames(frames)
+}
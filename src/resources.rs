@@ -1,8 +1,11 @@
 // resources.rs
 use crate::audio::AudioStore;
+use crate::components::{BoxColor, Coord, Position, Renderable};
 use crate::events::*;
-use ggez::event::KeyCode;
-use specs::World;
+use crate::palette::Palette;
+use ggez::{event::KeyCode, filesystem, Context};
+use specs::{shrev::EventChannel, Entity, World};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Display};
 use std::time::Duration;
 
@@ -10,6 +13,9 @@ use std::time::Duration;
 pub enum GameplayState {
     Playing,
     Won,
+    // Boxes fell into pits and too few are left to cover every goal, only
+    // undoing or restarting helps
+    Stuck,
 }
 impl Default for GameplayState {
     fn default() -> Self {
@@ -19,13 +25,40 @@ impl Default for GameplayState {
 
 #[derive(Default)]
 pub struct InputQueue {
-    pub keys_pressed: Vec<KeyCode>,
+    // Oldest first
+    pub keys_pressed: VecDeque<KeyCode>,
+    // Moves by co-op players with their seat, oldest first
+    pub seat_keys: Vec<(usize, KeyCode)>,
 }
 
 #[derive(Default)]
 pub struct Gameplay {
     pub state: GameplayState,
     pub moves_count: u32,
+    pub pushes_count: u32,
+    // What each co-op player did by seat, the counts above are the totals
+    pub seats: Vec<SeatCount>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct SeatCount {
+    pub moves: u32,
+    pub pushes: u32,
+}
+
+impl Gameplay {
+    // The moves shown under the board, each co-op player's on their own
+    pub fn moves_text(&self) -> String {
+        if self.seats.is_empty() {
+            return self.moves_count.to_string();
+        }
+        self.seats
+            .iter()
+            .enumerate()
+            .map(|(seat, count)| format!("P{} {}", seat + 1, count.moves))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
 }
 
 impl Display for GameplayState {
@@ -33,6 +66,7 @@ impl Display for GameplayState {
         fmt.write_str(match self {
             GameplayState::Playing => "Playing",
             GameplayState::Won => "Won",
+            GameplayState::Stuck => "Stuck",
         })?;
         Ok(())
     }
@@ -43,9 +77,83 @@ pub struct Time {
     pub delta: Duration,
 }
 
+// What each step changed, latest step last, so steps can be undone
+#[derive(Default)]
+pub struct History {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Default)]
+pub struct Step {
+    // Who moved
+    pub player: Option<Entity>,
+    // Where each entity moved was before it
+    pub moved: Vec<(Entity, Position)>,
+    // Boxes that fell into pits
+    pub swallowed: Vec<Swallowed>,
+    // Switches that were flipped
+    pub flipped: Vec<Entity>,
+    // How many boxes were pushed
+    pub pushes: u32,
+}
+
+// A box deleted by falling into a pit, with what's needed to put it back
+pub struct Swallowed {
+    pub entity: Entity,
+    pub pit: Entity,
+    // Where it was pushed from
+    pub position: Position,
+    pub color: BoxColor,
+    pub renderable: Renderable,
+}
+
+// The entities on each cell of the map, kept up to date as entities are
+// created and moved so systems can look cells up without joining over every
+// position
+#[derive(Default)]
+pub struct Grid {
+    cells: HashMap<(Coord, Coord), Vec<Entity>>,
+}
+
+impl Grid {
+    pub fn at(&self, x: Coord, y: Coord) -> &[Entity] {
+        self.cells.get(&(x, y)).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: &Position) {
+        self.cells
+            .entry((position.x, position.y))
+            .or_default()
+            .push(entity);
+    }
+
+    pub fn remove(&mut self, entity: Entity, position: &Position) {
+        if let Some(cell) = self.cells.get_mut(&(position.x, position.y)) {
+            cell.retain(|other| *other != entity);
+        }
+    }
+
+    pub fn move_entity(&mut self, entity: Entity, from: &Position, to: &Position) {
+        self.remove(entity, from);
+        self.insert(entity, to);
+    }
+}
+
+// Which image paths exist, looked up once each rather than every frame
 #[derive(Default)]
-pub struct EventQueue {
-    pub events: Vec<Event>,
+pub struct ImagePaths {
+    exists: HashMap<String, bool>,
+}
+
+impl ImagePaths {
+    pub fn exists(&mut self, context: &Context, path: &str) -> bool {
+        if let Some(exists) = self.exists.get(path) {
+            return *exists;
+        }
+        let exists = filesystem::exists(context, path);
+        self.exists.insert(path.to_string(), exists);
+        exists
+    }
 }
 
 // Registering resources
@@ -53,6 +161,10 @@ pub fn register_resources(world: &mut World) {
     world.insert(InputQueue::default());
     world.insert(Gameplay::default());
     world.insert(Time::default());
-    world.insert(EventQueue::default());
+    world.insert(EventChannel::<Event>::new());
+    world.insert(History::default());
     world.insert(AudioStore::default());
+    world.insert(Grid::default());
+    world.insert(ImagePaths::default());
+    world.insert(Palette::default());
 }
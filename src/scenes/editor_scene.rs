@@ -0,0 +1,405 @@
+use ggez::{
+    event::KeyCode,
+    filesystem,
+    graphics::{self, Color, Image},
+    Context, GameResult,
+};
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::{GameplayScene, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::constants::{MULTIPLIER, TEXT_PADDING, TEXT_SIZE, TILE_WIDTH};
+use crate::entities::gate_arrows;
+use crate::map::{Level, Tile};
+use crate::palette::Palette;
+use crate::resources::ImagePaths;
+use crate::ui::{draw_rect, draw_text, tile_param, TEXT_COLOR};
+
+const MIN_SIZE: usize = 3;
+const NEW_LEVEL_SIZE: usize = 8;
+const CURSOR_COLOR: Color = Color::new(0.8, 0.2, 0.2, 0.4);
+
+// Paint a level tile by tile, try it out and save it to the levels directory
+pub struct EditorScene {
+    level: Level,
+    name: Option<String>,
+    history: Vec<Level>,
+    brushes: Vec<Tile>,
+    brush: usize,
+    cursor: (usize, usize),
+    // Placing a player moves the one there is, unless several are allowed
+    one_player: bool,
+    status: String,
+    images: HashMap<String, Image>,
+    image_paths: ImagePaths,
+}
+
+impl EditorScene {
+    // Start from an empty room
+    pub fn new() -> Self {
+        let size = NEW_LEVEL_SIZE;
+        let tiles = (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| {
+                        if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                            Tile::Wall
+                        } else {
+                            Tile::Floor
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self::edit(Level::new(tiles), None)
+    }
+
+    // Edit an existing level. Only custom levels keep their name, others are
+    // saved as a new custom level.
+    pub fn edit(mut level: Level, name: Option<String>) -> Self {
+        // Pad ragged rows so every row can be painted up to the widest one
+        let cols = level.cols();
+        for row in level.tiles.iter_mut() {
+            row.resize(cols, Tile::Nothing);
+        }
+
+        // Boxes and spots of every color the palette knows about, with boxes
+        // and players starting on them
+        let colors = level.palette.colors();
+        let brushes = [Tile::Wall, Tile::Floor, Tile::Nothing, Tile::Player]
+            .into_iter()
+            .chain(colors.iter().map(|color| Tile::Box(*color)))
+            .chain(colors.iter().map(|color| Tile::BoxSpot(*color)))
+            .chain(colors.iter().map(|color| Tile::BoxOnSpot(*color, *color)))
+            .chain(colors.iter().map(|color| Tile::PlayerOnSpot(*color)))
+            .collect();
+
+        Self {
+            level,
+            name: name.filter(|name| name.starts_with("custom_")),
+            history: vec![],
+            brushes,
+            brush: 0,
+            cursor: (1, 1),
+            one_player: true,
+            status: String::new(),
+            images: HashMap::new(),
+            image_paths: ImagePaths::default(),
+        }
+    }
+
+    // Select the next brush after the current one that matches, so pressing
+    // the same key again cycles through the colors
+    fn select_brush(&mut self, matches: fn(&Tile) -> bool) {
+        let count = self.brushes.len();
+        if let Some(next) = (1..=count)
+            .map(|offset| (self.brush + offset) % count)
+            .find(|index| matches(&self.brushes[*index]))
+        {
+            self.brush = next;
+        }
+    }
+
+    fn paint(&mut self, x: usize, y: usize) {
+        let brush = self.brushes[self.brush];
+        if y >= self.level.rows() || x >= self.level.cols() || self.level.tiles[y][x] == brush {
+            return;
+        }
+
+        self.history.push(self.level.clone());
+
+        if self.one_player && brush.has_player() {
+            for tile in self.level.tiles.iter_mut().flatten() {
+                *tile = match *tile {
+                    Tile::Player => Tile::Floor,
+                    Tile::PlayerOnSpot(spot) => Tile::BoxSpot(spot),
+                    Tile::PlayerOnTeleporter(id) => Tile::Teleporter(id),
+                    Tile::PlayerOnIce => Tile::Ice,
+                    Tile::PlayerOnGate(gate) => Tile::Gate(gate),
+                    Tile::PlayerOnPlate(id) => Tile::Plate(id),
+                    Tile::PlayerOnSwitch(switch) => Tile::Switch(switch),
+                    Tile::PlayerOnDoor(id) => Tile::Door(id),
+                    tile => tile,
+                };
+            }
+        }
+        self.level.tiles[y][x] = brush;
+    }
+
+    fn resize(&mut self, context: &Context, rows: isize, cols: isize) {
+        let (max_rows, max_cols) = max_size(context);
+        let new_rows = (self.level.rows() as isize + rows).clamp(MIN_SIZE as isize, max_rows);
+        let new_cols = (self.level.cols() as isize + cols).clamp(MIN_SIZE as isize, max_cols);
+        if new_rows as usize == self.level.rows() && new_cols as usize == self.level.cols() {
+            return;
+        }
+
+        self.history.push(self.level.clone());
+        self.level
+            .tiles
+            .resize(new_rows as usize, vec![Tile::Nothing; new_cols as usize]);
+        for row in self.level.tiles.iter_mut() {
+            row.resize(new_cols as usize, Tile::Nothing);
+        }
+        self.cursor.0 = self.cursor.0.min(self.level.cols() - 1);
+        self.cursor.1 = self.cursor.1.min(self.level.rows() - 1);
+    }
+
+    fn undo(&mut self) {
+        match self.history.pop() {
+            Some(level) => self.level = level,
+            None => self.status = "Nothing to undo".to_string(),
+        }
+    }
+
+    fn has_player(&self) -> bool {
+        self.level.tiles.iter().flatten().any(Tile::has_player)
+    }
+
+    fn test_play(&mut self, context: &mut Context) -> Transition {
+        if !self.has_player() {
+            self.status = "Place the player first".to_string();
+            return Transition::None;
+        }
+
+        Transition::Push(Box::new(GameplayScene::new(
+            context,
+            self.level.clone(),
+            None,
+        )))
+    }
+
+    fn save(&mut self, context: &mut Context, campaign: &mut Campaign) {
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => (1..)
+                .map(|number| format!("custom_{:02}", number))
+                .find(|name| campaign.levels.iter().all(|entry| &entry.name != name))
+                .expect("expected a free level name"),
+        };
+
+        let path = format!("/levels/{}.txt", name);
+        let saved = filesystem::create_dir(context, "/levels")
+            .and_then(|_| filesystem::create(context, &path))
+            .and_then(|mut file| Ok(file.write_all(self.level.to_map_string().as_bytes())?));
+
+        self.status = match saved {
+            Ok(_) => {
+                // Reload so the new level shows up in the level select screen
+                *campaign = Campaign::load(context);
+                self.name = Some(name.clone());
+                format!("Saved {}", name)
+            }
+            Err(error) => format!("Could not save: {}", error),
+        };
+    }
+
+    fn image(&mut self, context: &mut Context, path: &str) -> Image {
+        self.images
+            .entry(path.to_string())
+            .or_insert_with(|| Image::new(context, path).expect("expected image"))
+            .clone()
+    }
+
+    fn draw_image(
+        &mut self,
+        context: &mut Context,
+        path: &str,
+        (x, y): (usize, usize),
+        color: Color,
+        quarter_turns: u8,
+    ) {
+        let image = self.image(context, path);
+        let draw_params = tile_param(
+            x as f32 * TILE_WIDTH * MULTIPLIER,
+            y as f32 * TILE_WIDTH * MULTIPLIER,
+            quarter_turns,
+        )
+        .color(color);
+
+        graphics::draw(context, &image, draw_params).expect("expected render");
+    }
+}
+
+impl Default for EditorScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for EditorScene {
+    fn update(&mut self, _context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        let tiles: Vec<(usize, usize, Tile)> = self
+            .level
+            .tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, tile)| (x, y, *tile)))
+            .collect();
+
+        for (x, y, tile) in tiles {
+            if tile != Tile::Nothing {
+                self.draw_image(context, "/images/floor.png", (x, y), Color::WHITE, 0);
+            }
+            for (path, quarter_turns) in tile.gate().iter().flat_map(gate_arrows) {
+                self.draw_image(context, &path, (x, y), Color::WHITE, quarter_turns);
+            }
+            if let Some((path, color)) =
+                tile_image(context, &mut self.image_paths, &self.level.palette, tile)
+            {
+                self.draw_image(context, &path, (x, y), color, 0);
+            }
+        }
+
+        let (x, y) = self.cursor;
+        draw_rect(
+            context,
+            x as f32 * TILE_WIDTH,
+            y as f32 * TILE_WIDTH,
+            TILE_WIDTH,
+            TILE_WIDTH,
+            CURSOR_COLOR,
+        );
+
+        let bottom = TILE_WIDTH * max_size(context).0 as f32;
+        let brush = self.brushes[self.brush];
+        draw_text(
+            context,
+            &format!(
+                "Brush: {} Players: {} {}",
+                brush.token(),
+                if self.one_player { "one" } else { "several" },
+                self.status
+            ),
+            TEXT_PADDING,
+            bottom + TEXT_PADDING,
+            TEXT_COLOR,
+        );
+        draw_text(
+            context,
+            "1-8 brush P players T test S save U undo []-= size",
+            TEXT_PADDING,
+            bottom + TEXT_SIZE + TEXT_PADDING,
+            TEXT_COLOR,
+        );
+
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        self.status.clear();
+        let (x, y) = self.cursor;
+
+        match keycode {
+            KeyCode::Escape => return Transition::Pop(1),
+            KeyCode::Up => self.cursor.1 = y.saturating_sub(1),
+            KeyCode::Down => self.cursor.1 = (y + 1).min(self.level.rows() - 1),
+            KeyCode::Left => self.cursor.0 = x.saturating_sub(1),
+            KeyCode::Right => self.cursor.0 = (x + 1).min(self.level.cols() - 1),
+            KeyCode::Space | KeyCode::Return => self.paint(x, y),
+            KeyCode::Tab => self.brush = (self.brush + 1) % self.brushes.len(),
+            KeyCode::Key1 => self.select_brush(|tile| *tile == Tile::Wall),
+            KeyCode::Key2 => self.select_brush(|tile| *tile == Tile::Floor),
+            KeyCode::Key3 => self.select_brush(|tile| *tile == Tile::Nothing),
+            KeyCode::Key4 => self.select_brush(|tile| *tile == Tile::Player),
+            KeyCode::Key5 => self.select_brush(|tile| matches!(tile, Tile::Box(_))),
+            KeyCode::Key6 => self.select_brush(|tile| matches!(tile, Tile::BoxSpot(_))),
+            KeyCode::Key7 => self.select_brush(|tile| matches!(tile, Tile::BoxOnSpot(..))),
+            KeyCode::Key8 => self.select_brush(|tile| matches!(tile, Tile::PlayerOnSpot(_))),
+            KeyCode::P => self.one_player = !self.one_player,
+            KeyCode::LBracket => self.resize(context, 0, -1),
+            KeyCode::RBracket => self.resize(context, 0, 1),
+            KeyCode::Minus => self.resize(context, -1, 0),
+            KeyCode::Equals => self.resize(context, 1, 0),
+            KeyCode::U => self.undo(),
+            KeyCode::S => self.save(context, campaign),
+            KeyCode::T => return self.test_play(context),
+            _ => (),
+        }
+
+        Transition::None
+    }
+
+    fn mouse_down(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        let (x, y) = ((x / TILE_WIDTH) as usize, (y / TILE_WIDTH) as usize);
+        if y < self.level.rows() && x < self.level.cols() {
+            self.cursor = (x, y);
+            self.paint(x, y);
+        }
+
+        Transition::None
+    }
+}
+
+// The image drawn on top of the floor for a tile, and its tint
+fn tile_image(
+    context: &Context,
+    image_paths: &mut ImagePaths,
+    palette: &Palette,
+    tile: Tile,
+) -> Option<(String, Color)> {
+    let (path, fallback, color) = match tile {
+        Tile::Nothing | Tile::Floor | Tile::Gate(_) => return None,
+        Tile::Wall => return Some(("/images/wall.png".to_string(), Color::WHITE)),
+        Tile::Teleporter(_) => return Some(("/images/teleporter.png".to_string(), Color::WHITE)),
+        Tile::Ice => return Some(("/images/ice.png".to_string(), Color::WHITE)),
+        Tile::Pit => return Some(("/images/pit.png".to_string(), Color::WHITE)),
+        Tile::Plate(_) => return Some(("/images/plate.png".to_string(), Color::WHITE)),
+        Tile::Switch(switch) => return Some((switch.image(), Color::WHITE)),
+        Tile::Door(_) => return Some(("/images/door.png".to_string(), Color::WHITE)),
+        Tile::Player
+        | Tile::PlayerOnSpot(_)
+        | Tile::PlayerOnTeleporter(_)
+        | Tile::PlayerOnIce
+        | Tile::PlayerOnGate(_)
+        | Tile::PlayerOnPlate(_)
+        | Tile::PlayerOnSwitch(_)
+        | Tile::PlayerOnDoor(_) => return Some(("/images/player_1.png".to_string(), Color::WHITE)),
+        Tile::Box(color)
+        | Tile::BoxOnSpot(color, _)
+        | Tile::BoxOnTeleporter(color, _)
+        | Tile::BoxOnIce(color)
+        | Tile::BoxOnGate(color, _)
+        | Tile::BoxOnPlate(color, _)
+        | Tile::BoxOnSwitch(color, _)
+        | Tile::BoxOnDoor(color, _) => ("box", "/images/box.png", palette.get(color)),
+        Tile::BoxSpot(color) => ("box_spot", "/images/box_spot.png", palette.get(color)),
+    };
+
+    let path = format!("/images/{}_{}.png", path, color.name);
+    if image_paths.exists(context, &path) {
+        Some((path, Color::WHITE))
+    } else {
+        let [r, g, b] = color.rgb;
+        Some((fallback.to_string(), Color::from_rgb(r, g, b)))
+    }
+}
+
+// How many rows and columns of tiles fit in the window
+fn max_size(context: &Context) -> (isize, isize) {
+    let screen = graphics::screen_coordinates(context);
+    let tile = TILE_WIDTH * MULTIPLIER;
+    let hud = (TEXT_SIZE + TEXT_PADDING) * 2.0 * MULTIPLIER;
+
+    (
+        ((screen.h - hud) / tile) as isize,
+        (screen.w / tile) as isize,
+    )
+}
@@ -0,0 +1,279 @@
+use ggez::{event::KeyCode, filesystem, Context, GameResult};
+use serde_json::{json, Value};
+use specs::{
+    shrev::{EventChannel, ReaderId},
+    Join, WorldExt,
+};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::level_world::LevelWorld;
+use super::{PauseScene, ResultsScene, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::canonical::level_hash;
+use crate::components::{Active, BoxSpot, Player, Position};
+use crate::constants::MULTIPLIER;
+use crate::entities::seat_players;
+use crate::env::Action;
+use crate::events::Event;
+use crate::map::Level;
+use crate::remote::{event_message, Command};
+use crate::resources::*;
+use crate::screenshot::{Screenshot, RESOURCES_DIR};
+
+// Playing a level. Owns the ECS world for that level.
+pub struct GameplayScene {
+    level_world: LevelWorld,
+    // Where remote clients are up to
+    remote_reader: ReaderId<Event>,
+    level: Level,
+    level_index: Option<usize>,
+    // How many people play together with their own keys, none when one
+    // person plays
+    seats: usize,
+}
+
+// The keys each co-op player moves with, in LURD order. A gamepad's d-pad
+// sends the arrows, so it can stand in for player two.
+pub const SEAT_KEYS: [[KeyCode; 4]; 2] = [
+    [KeyCode::A, KeyCode::W, KeyCode::D, KeyCode::S],
+    [KeyCode::Left, KeyCode::Up, KeyCode::Right, KeyCode::Down],
+];
+
+pub const SEATS: usize = SEAT_KEYS.len();
+
+impl GameplayScene {
+    // Play a level. Levels from the campaign pass their index so winning them
+    // is recorded, levels being tried out from the editor don't have one.
+    pub fn new(context: &mut Context, level: Level, level_index: Option<usize>) -> Self {
+        let mut level_world = LevelWorld::new(context, &level);
+        let remote_reader = level_world.register_reader();
+        level_world.start();
+
+        Self {
+            level_world,
+            remote_reader,
+            level,
+            level_index,
+            seats: 0,
+        }
+    }
+
+    // Play co-op, the first `seats` players each get their own keys. Levels
+    // with fewer players seat as many as there are.
+    pub fn with_seats(mut self, seats: usize) -> Self {
+        if seats > 0 {
+            self.seats = seat_players(&mut self.level_world.world, seats);
+        }
+        self
+    }
+
+    // The co-op player and direction a key moves, if it's one of theirs
+    fn seat_key(&self, keycode: KeyCode) -> Option<(usize, KeyCode)> {
+        SEAT_KEYS
+            .iter()
+            .take(self.seats)
+            .enumerate()
+            .find_map(|(seat, keys)| {
+                let direction = keys.iter().position(|key| *key == keycode)?;
+                Some((seat, Action::ALL[direction].key()))
+            })
+    }
+
+    // Save what's on screen to the user directory as a PNG
+    fn screenshot(&self, context: &mut Context) {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let path = format!(
+            "/screenshots/{:016x}_{}.png",
+            level_hash(&self.level),
+            seconds
+        );
+
+        let mut screenshot = Screenshot::new(Path::new(RESOURCES_DIR), MULTIPLIER as u32);
+        screenshot.hud = true;
+        let saved = screenshot
+            .encode(&self.level_world.world)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| {
+                filesystem::create_dir(context, "/screenshots")
+                    .and_then(|_| filesystem::create(context, &path))
+                    .and_then(|mut file| Ok(file.write_all(&bytes)?))
+                    .map_err(|error| error.to_string())
+            });
+        match saved {
+            Ok(_) => println!("Saved screenshot {}", path),
+            Err(error) => println!("Could not save screenshot: {}", error),
+        }
+    }
+
+    pub fn from_campaign(context: &mut Context, campaign: &Campaign, level_index: usize) -> Self {
+        let level = campaign.levels[level_index].level.clone();
+        Self::new(context, level, Some(level_index))
+    }
+}
+
+impl Scene for GameplayScene {
+    fn update(&mut self, context: &mut Context, campaign: &mut Campaign) -> Transition {
+        self.level_world.update(context);
+
+        let gameplay = self.level_world.world.read_resource::<Gameplay>();
+        match gameplay.state {
+            GameplayState::Won => {
+                if let Some(level_index) = self.level_index {
+                    campaign.mark_solved(context, level_index);
+                }
+                Transition::Push(Box::new(ResultsScene::new(
+                    campaign,
+                    self.level.clone(),
+                    self.level_index,
+                    self.seats,
+                    gameplay.moves_count,
+                    gameplay.pushes_count,
+                )))
+            }
+            GameplayState::Playing | GameplayState::Stuck => Transition::None,
+        }
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        self.level_world.draw(context);
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        _campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        if keycode == KeyCode::F12 {
+            self.screenshot(context);
+            return Transition::None;
+        }
+        if keycode == KeyCode::Escape {
+            return Transition::Push(Box::new(PauseScene::new(
+                self.level.clone(),
+                self.level_index,
+                self.seats,
+            )));
+        }
+
+        let mut input_queue = self.level_world.world.write_resource::<InputQueue>();
+        match self.seat_key(keycode) {
+            Some(seat_key) => input_queue.seat_keys.push(seat_key),
+            None => input_queue.keys_pressed.push_back(keycode),
+        }
+        Transition::None
+    }
+
+    fn remote_command(
+        &mut self,
+        context: &mut Context,
+        _campaign: &mut Campaign,
+        command: &Command,
+    ) -> Result<Transition, String> {
+        let keycode = match command {
+            Command::Move {
+                direction,
+                seat: Some(seat),
+            } if *seat < self.seats => {
+                let mut input_queue = self.level_world.world.write_resource::<InputQueue>();
+                input_queue.seat_keys.push((*seat, direction.key()));
+                return Ok(Transition::None);
+            }
+            // Nobody moves with the plain keys in co-op
+            Command::Move { .. } if self.seats > 0 => {
+                return Err(format!(
+                    "co-op moves need a seat from 0 to {}",
+                    self.seats - 1
+                ))
+            }
+            Command::Move { direction, .. } => direction.key(),
+            Command::Undo => KeyCode::U,
+            Command::SwitchPlayer => KeyCode::Tab,
+            Command::Restart => {
+                return Ok(Transition::Replace(
+                    1,
+                    Box::new(
+                        GameplayScene::new(context, self.level.clone(), self.level_index)
+                            .with_seats(self.seats),
+                    ),
+                ))
+            }
+            _ => return Err("no level is being played".to_string()),
+        };
+
+        let mut input_queue = self.level_world.world.write_resource::<InputQueue>();
+        input_queue.keys_pressed.push_back(keycode);
+        Ok(Transition::None)
+    }
+
+    fn remote_state(&self) -> Option<Value> {
+        let positions = self.level_world.world.read_storage::<Position>();
+        let players = self.level_world.world.read_storage::<Player>();
+        let actives = self.level_world.world.read_storage::<Active>();
+        let boxes = self
+            .level_world
+            .world
+            .read_storage::<crate::components::Box>();
+        let box_spots = self.level_world.world.read_storage::<BoxSpot>();
+        let gameplay = self.level_world.world.read_resource::<Gameplay>();
+
+        let player = (&positions, &actives)
+            .join()
+            .map(|(position, _)| json!([position.x, position.y]))
+            .next();
+        let others: Vec<Value> = (&positions, &players, !&actives)
+            .join()
+            .map(|(position, _, _)| json!([position.x, position.y]))
+            .collect();
+        let boxes: Vec<Value> = (&positions, &boxes)
+            .join()
+            .map(|(position, the_box)| {
+                json!({ "x": position.x, "y": position.y, "color": the_box.color.to_string() })
+            })
+            .collect();
+        let goals: Vec<Value> = (&positions, &box_spots)
+            .join()
+            .map(|(position, box_spot)| {
+                json!({ "x": position.x, "y": position.y, "color": box_spot.color.to_string() })
+            })
+            .collect();
+
+        Some(json!({
+            "type": "state",
+            "level": format!("{:016x}", level_hash(&self.level)),
+            "level_index": self.level_index,
+            "map": self.level.to_map_string(),
+            "rows": self.level_world.rows,
+            "cols": self.level_world.cols,
+            "state": gameplay.state.to_string(),
+            "moves": gameplay.moves_count,
+            "pushes": gameplay.pushes_count,
+            "player": player,
+            "other_players": others,
+            "seats": gameplay
+                .seats
+                .iter()
+                .map(|count| json!({ "moves": count.moves, "pushes": count.pushes }))
+                .collect::<Vec<_>>(),
+            "boxes": boxes,
+            "goals": goals,
+        }))
+    }
+
+    fn remote_events(&mut self) -> Vec<Value> {
+        let events = self
+            .level_world
+            .world
+            .read_resource::<EventChannel<Event>>();
+        events
+            .read(&mut self.remote_reader)
+            .map(event_message)
+            .collect()
+    }
+}
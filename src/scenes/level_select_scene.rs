@@ -0,0 +1,170 @@
+use ggez::{event::KeyCode, graphics::Color, Context, GameResult};
+
+use super::{EditorScene, GameplayScene, RaceScene, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::ui::{draw_rect, draw_text, HIGHLIGHT_COLOR, TEXT_COLOR};
+
+const GRID_COLUMNS: usize = 4;
+const GRID_LEFT: f32 = 16.0;
+const GRID_TOP: f32 = 40.0;
+const CELL_SIZE: f32 = 40.0;
+const CELL_PADDING: f32 = 4.0;
+
+const SOLVED_COLOR: Color = Color::new(0.55, 0.8, 0.55, 1.0);
+const UNSOLVED_COLOR: Color = Color::new(0.8, 0.8, 0.8, 1.0);
+// Levels without a player for everyone playing co-op
+const UNPLAYABLE_COLOR: Color = Color::new(0.45, 0.45, 0.45, 1.0);
+
+// A grid of all the levels, solved ones are shown in green. Co-op can only
+// start levels with a player for each person, the rest are greyed out.
+pub struct LevelSelectScene {
+    selected: usize,
+    // Levels are played co-op by this many people when there are any
+    seats: usize,
+    // Levels are raced side by side instead
+    race: bool,
+}
+
+impl LevelSelectScene {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            seats: 0,
+            race: false,
+        }
+    }
+
+    pub fn racing(self) -> Self {
+        Self { race: true, ..self }
+    }
+
+    pub fn with_seats(self, seats: usize) -> Self {
+        Self { seats, ..self }
+    }
+
+    fn start(&self, context: &mut Context, campaign: &Campaign) -> Transition {
+        if !campaign.has_seats_for(self.selected, self.seats) {
+            return Transition::None;
+        }
+        if self.race {
+            let level = campaign.levels[self.selected].level.clone();
+            return Transition::Push(Box::new(RaceScene::new(context, level)));
+        }
+        Transition::Push(Box::new(
+            GameplayScene::from_campaign(context, campaign, self.selected).with_seats(self.seats),
+        ))
+    }
+}
+
+impl Default for LevelSelectScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for LevelSelectScene {
+    fn update(&mut self, _context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut Context, campaign: &Campaign) -> GameResult {
+        let title = if self.race {
+            "Select a level to race (E to edit)"
+        } else if self.seats > 0 {
+            "Select a level to play co-op (E to edit)"
+        } else {
+            "Select a level (E to edit)"
+        };
+        draw_text(context, title, GRID_LEFT, 16.0, TEXT_COLOR);
+
+        for index in 0..campaign.levels.len() {
+            let x = GRID_LEFT + (index % GRID_COLUMNS) as f32 * CELL_SIZE;
+            let y = GRID_TOP + (index / GRID_COLUMNS) as f32 * CELL_SIZE;
+            let size = CELL_SIZE - CELL_PADDING;
+
+            if index == self.selected {
+                draw_rect(
+                    context,
+                    x - 2.0,
+                    y - 2.0,
+                    size + 4.0,
+                    size + 4.0,
+                    HIGHLIGHT_COLOR,
+                );
+            }
+            let color = if !campaign.has_seats_for(index, self.seats) {
+                UNPLAYABLE_COLOR
+            } else if campaign.is_solved(index) {
+                SOLVED_COLOR
+            } else {
+                UNSOLVED_COLOR
+            };
+            draw_rect(context, x, y, size, size, color);
+            draw_text(
+                context,
+                &(index + 1).to_string(),
+                x + CELL_PADDING,
+                y + CELL_PADDING,
+                TEXT_COLOR,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        let count = campaign.levels.len();
+        if count == 0 {
+            return match keycode {
+                KeyCode::Escape => Transition::Pop(1),
+                _ => Transition::None,
+            };
+        }
+
+        match keycode {
+            KeyCode::Left => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Right => self.selected = (self.selected + 1) % count,
+            KeyCode::Up if self.selected >= GRID_COLUMNS => self.selected -= GRID_COLUMNS,
+            KeyCode::Down if self.selected + GRID_COLUMNS < count => self.selected += GRID_COLUMNS,
+            KeyCode::Return | KeyCode::Space => return self.start(context, campaign),
+            KeyCode::E => {
+                let entry = &campaign.levels[self.selected];
+                return Transition::Push(Box::new(EditorScene::edit(
+                    entry.level.clone(),
+                    Some(entry.name.clone()),
+                )));
+            }
+            KeyCode::Escape => return Transition::Pop(1),
+            _ => (),
+        }
+
+        Transition::None
+    }
+
+    fn mouse_down(
+        &mut self,
+        context: &mut Context,
+        campaign: &mut Campaign,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        if x < GRID_LEFT || y < GRID_TOP {
+            return Transition::None;
+        }
+
+        let column = ((x - GRID_LEFT) / CELL_SIZE) as usize;
+        let row = ((y - GRID_TOP) / CELL_SIZE) as usize;
+        let index = row * GRID_COLUMNS + column;
+        if column >= GRID_COLUMNS || index >= campaign.levels.len() {
+            return Transition::None;
+        }
+
+        self.selected = index;
+        self.start(context, campaign)
+    }
+}
@@ -0,0 +1,94 @@
+use ggez::{timer, Context};
+use specs::{
+    shrev::{EventChannel, ReaderId},
+    Dispatcher, DispatcherBuilder, RunNow, World, WorldExt,
+};
+
+use crate::audio::initialize_sounds;
+use crate::components::{register_components, Coord};
+use crate::events::Event;
+use crate::map::{load_map, Level};
+use crate::resources::*;
+use crate::systems::*;
+
+// A level loaded into its own world with the systems that play it, its
+// sounds and drawing. Every scene that plays a level has one.
+pub struct LevelWorld {
+    pub world: World,
+    dispatcher: Dispatcher<'static, 'static>,
+    // Where sounds are up to in the event stream
+    audio_reader: ReaderId<Event>,
+    pub rows: Coord,
+    pub cols: Coord,
+}
+
+impl LevelWorld {
+    // Load a level, it begins once `start` is called
+    pub fn new(context: &mut Context, level: &Level) -> Self {
+        let mut world = World::new();
+        register_components(&mut world);
+        register_resources(&mut world);
+
+        let (rows, cols) = load_map(&mut world, level);
+
+        // One after the other in the same order as `Headless`, so a frame
+        // plays out the same in the window and without one
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(InputSystem { cols, rows }, "input", &[])
+            .with(DoorSystem {}, "doors", &["input"])
+            .with(StatsSystem::default(), "stats", &["doors"])
+            .with(GameplayStateSystem {}, "gameplay", &["stats"])
+            .build();
+        dispatcher.setup(&mut world);
+        initialize_sounds(&world, context);
+
+        let audio_reader = world
+            .write_resource::<EventChannel<Event>>()
+            .register_reader();
+
+        Self {
+            world,
+            dispatcher,
+            audio_reader,
+            rows,
+            cols,
+        }
+    }
+
+    // Readers only see events written after they registered, so ones that
+    // want the whole level register before it starts
+    pub fn register_reader(&mut self) -> ReaderId<Event> {
+        self.world
+            .write_resource::<EventChannel<Event>>()
+            .register_reader()
+    }
+
+    pub fn start(&mut self) {
+        self.world
+            .write_resource::<EventChannel<Event>>()
+            .single_write(Event::LevelStarted);
+    }
+
+    // Play the keys pressed since the last update and their sounds
+    pub fn update(&mut self, context: &mut Context) {
+        self.dispatcher.dispatch(&self.world);
+        {
+            let mut es = EventSystem {
+                context,
+                reader: &mut self.audio_reader,
+            };
+            es.run_now(&self.world);
+        }
+        self.world.maintain();
+        self.world.write_resource::<Time>().delta += timer::delta(context);
+    }
+
+    pub fn draw(&self, context: &mut Context) {
+        let mut rs = RenderingSystem {
+            context,
+            cols: self.cols,
+            rows: self.rows,
+        };
+        rs.run_now(&self.world);
+    }
+}
@@ -0,0 +1,88 @@
+use ggez::{event::KeyCode, Context, GameResult};
+
+use super::gameplay_scene::SEATS;
+use super::{EditorScene, LevelSelectScene, MenuList, PracticeScene, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::ui::{draw_text, TEXT_COLOR};
+
+const PLAY: usize = 0;
+const CO_OP: usize = 1;
+const RACE: usize = 2;
+const PRACTICE: usize = 3;
+const EDITOR: usize = 4;
+const QUIT: usize = 5;
+
+// The title screen
+pub struct MenuScene {
+    menu: MenuList,
+}
+
+impl MenuScene {
+    pub fn new() -> Self {
+        Self {
+            menu: MenuList::new(
+                vec!["Play", "Co-op", "Race", "Practice", "Editor", "Quit"],
+                16.0,
+                48.0,
+            ),
+        }
+    }
+
+    fn pick(&self, index: usize) -> Transition {
+        match index {
+            PLAY => Transition::Push(Box::new(LevelSelectScene::new())),
+            CO_OP => Transition::Push(Box::new(LevelSelectScene::new().with_seats(SEATS))),
+            RACE => Transition::Push(Box::new(LevelSelectScene::new().racing())),
+            PRACTICE => Transition::Push(Box::new(PracticeScene::new())),
+            EDITOR => Transition::Push(Box::new(EditorScene::new())),
+            QUIT => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+}
+
+impl Default for MenuScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for MenuScene {
+    fn update(&mut self, _context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        draw_text(context, "Rust Sokoban", 16.0, 16.0, TEXT_COLOR);
+        self.menu.draw(context);
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        match keycode {
+            KeyCode::Escape => Transition::Quit,
+            _ => match self.menu.key_down(keycode) {
+                Some(index) => self.pick(index),
+                None => Transition::None,
+            },
+        }
+    }
+
+    fn mouse_down(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_down(x, y) {
+            Some(index) => self.pick(index),
+            None => Transition::None,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use ggez::{event::KeyCode, Context, GameResult};
+use serde_json::Value;
+
+use crate::campaign::Campaign;
+use crate::remote::Command;
+use crate::ui::{draw_text, HIGHLIGHT_COLOR, TEXT_COLOR};
+
+mod editor_scene;
+mod gameplay_scene;
+mod level_select_scene;
+mod level_world;
+mod menu_scene;
+mod pause_scene;
+mod practice_scene;
+mod race_scene;
+mod results_scene;
+
+pub use self::editor_scene::EditorScene;
+pub use self::gameplay_scene::GameplayScene;
+pub use self::level_select_scene::LevelSelectScene;
+pub use self::menu_scene::MenuScene;
+pub use self::pause_scene::PauseScene;
+pub use self::practice_scene::PracticeScene;
+pub use self::race_scene::RaceScene;
+pub use self::results_scene::ResultsScene;
+
+// What the scene stack should do after a scene handled an update or input
+pub enum Transition {
+    None,
+    Push(Box<dyn Scene>),
+    // Pop this many scenes off the stack
+    Pop(usize),
+    // Pop this many scenes off the stack, then push a new one
+    Replace(usize, Box<dyn Scene>),
+    Quit,
+}
+
+// A screen of the game. Only the scene at the top of the stack is updated and
+// receives input, overlays also get the scenes below them drawn first.
+pub trait Scene {
+    fn update(&mut self, context: &mut Context, campaign: &mut Campaign) -> Transition;
+
+    fn draw(&mut self, context: &mut Context, campaign: &Campaign) -> GameResult;
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition;
+
+    // Mouse coordinates are in unscaled pixels
+    fn mouse_down(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        _x: f32,
+        _y: f32,
+    ) -> Transition {
+        Transition::None
+    }
+
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    // A command from a remote client. Returns why if the scene can't take
+    // it, scenes that aren't playing a level can't take any.
+    fn remote_command(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        _command: &Command,
+    ) -> Result<Transition, String> {
+        Err("no level is being played".to_string())
+    }
+
+    // The state reported to remote clients
+    fn remote_state(&self) -> Option<Value> {
+        None
+    }
+
+    // Events since the last call, as messages for remote clients
+    fn remote_events(&mut self) -> Vec<Value> {
+        vec![]
+    }
+}
+
+const MENU_LINE_HEIGHT: f32 = 16.0;
+
+// A vertical list of options that can be picked with the keyboard or mouse
+pub struct MenuList {
+    pub items: Vec<&'static str>,
+    pub selected: usize,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl MenuList {
+    pub fn new(items: Vec<&'static str>, x: f32, y: f32) -> Self {
+        Self {
+            items,
+            selected: 0,
+            x,
+            y,
+        }
+    }
+
+    // Move the selection, returns the picked item index on enter
+    pub fn key_down(&mut self, keycode: KeyCode) -> Option<usize> {
+        match keycode {
+            KeyCode::Up => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                None
+            }
+            KeyCode::Down => {
+                self.selected = (self.selected + 1) % self.items.len();
+                None
+            }
+            KeyCode::Return | KeyCode::Space => Some(self.selected),
+            _ => None,
+        }
+    }
+
+    // Returns the item index under the mouse, if any
+    pub fn mouse_down(&mut self, _x: f32, y: f32) -> Option<usize> {
+        if y < self.y {
+            return None;
+        }
+
+        let index = ((y - self.y) / MENU_LINE_HEIGHT) as usize;
+        if index < self.items.len() {
+            self.selected = index;
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    pub fn draw(&self, context: &mut Context) {
+        for (index, item) in self.items.iter().enumerate() {
+            let (text, color) = if index == self.selected {
+                (format!("> {}", item), HIGHLIGHT_COLOR)
+            } else {
+                (format!("  {}", item), TEXT_COLOR)
+            };
+
+            draw_text(
+                context,
+                &text,
+                self.x,
+                self.y + index as f32 * MENU_LINE_HEIGHT,
+                color,
+            );
+        }
+    }
+}
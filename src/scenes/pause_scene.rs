@@ -0,0 +1,95 @@
+use ggez::{event::KeyCode, Context, GameResult};
+
+use super::{GameplayScene, MenuList, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::map::Level;
+use crate::ui::{draw_shade, draw_text, TEXT_COLOR};
+
+const RESUME: &str = "Resume";
+const RESTART: &str = "Restart";
+const LEVEL_SELECT: &str = "Level select";
+const BACK: &str = "Back";
+
+// Drawn on top of a paused level
+pub struct PauseScene {
+    menu: MenuList,
+    level: Level,
+    level_index: Option<usize>,
+    seats: usize,
+}
+
+impl PauseScene {
+    pub fn new(level: Level, level_index: Option<usize>, seats: usize) -> Self {
+        let back = match level_index {
+            Some(_) => LEVEL_SELECT,
+            None => BACK,
+        };
+
+        Self {
+            menu: MenuList::new(vec![RESUME, RESTART, back], 16.0, 48.0),
+            level,
+            level_index,
+            seats,
+        }
+    }
+
+    fn pick(&self, context: &mut Context, index: usize) -> Transition {
+        match self.menu.items[index] {
+            RESUME => Transition::Pop(1),
+            RESTART => Transition::Replace(
+                2,
+                Box::new(
+                    GameplayScene::new(context, self.level.clone(), self.level_index)
+                        .with_seats(self.seats),
+                ),
+            ),
+            LEVEL_SELECT | BACK => Transition::Pop(2),
+            _ => Transition::None,
+        }
+    }
+}
+
+impl Scene for PauseScene {
+    fn update(&mut self, _context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        draw_shade(context);
+        draw_text(context, "Paused", 16.0, 16.0, TEXT_COLOR);
+        self.menu.draw(context);
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        _campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        match keycode {
+            KeyCode::Escape => Transition::Pop(1),
+            _ => match self.menu.key_down(keycode) {
+                Some(index) => self.pick(context, index),
+                None => Transition::None,
+            },
+        }
+    }
+
+    fn mouse_down(
+        &mut self,
+        context: &mut Context,
+        _campaign: &mut Campaign,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_down(x, y) {
+            Some(index) => self.pick(context, index),
+            None => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,132 @@
+use ggez::{event::KeyCode, Context, GameResult};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use super::{GameplayScene, MenuList, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::generator::{generate, GeneratedLevel, GeneratorConfig};
+use crate::ui::{draw_text, TEXT_COLOR};
+
+const EASY: &str = "Easy";
+const MEDIUM: &str = "Medium";
+const HARD: &str = "Hard";
+const BACK: &str = "Back";
+
+// Pick a difficulty and play a freshly generated level
+pub struct PracticeScene {
+    menu: MenuList,
+    status: String,
+    // The level being generated on another thread, so the window keeps
+    // drawing while bigger levels are searched for
+    generating: Option<Receiver<Option<GeneratedLevel>>>,
+}
+
+impl PracticeScene {
+    pub fn new() -> Self {
+        Self {
+            menu: MenuList::new(vec![EASY, MEDIUM, HARD, BACK], 16.0, 48.0),
+            status: String::new(),
+            generating: None,
+        }
+    }
+
+    fn pick(&mut self, index: usize) -> Transition {
+        let config = match self.menu.items[index] {
+            EASY => config(7, 7, 2, 1),
+            MEDIUM => config(8, 8, 3, 2),
+            HARD => config(9, 9, 4, 2),
+            BACK => return Transition::Pop(1),
+            _ => return Transition::None,
+        };
+
+        // Only one level is generated at a time
+        if self.generating.is_some() {
+            return Transition::None;
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(generate(&config, &mut rand::thread_rng()));
+        });
+        self.generating = Some(receiver);
+        self.status = "Generating...".to_string();
+        Transition::None
+    }
+
+    // Start the level once it has been generated
+    fn poll(&mut self, context: &mut Context) -> Transition {
+        let generated = match self.generating.as_ref().map(Receiver::try_recv) {
+            None | Some(Err(TryRecvError::Empty)) => return Transition::None,
+            Some(Ok(generated)) => generated,
+            Some(Err(TryRecvError::Disconnected)) => None,
+        };
+        self.generating = None;
+
+        match generated {
+            Some(generated) => {
+                self.status.clear();
+                Transition::Push(Box::new(GameplayScene::new(context, generated.level, None)))
+            }
+            None => {
+                self.status = "Try again".to_string();
+                Transition::None
+            }
+        }
+    }
+}
+
+fn config(rows: usize, cols: usize, boxes: usize, colors: usize) -> GeneratorConfig {
+    GeneratorConfig {
+        rows,
+        cols,
+        boxes,
+        colors,
+        attempts: 10,
+    }
+}
+
+impl Default for PracticeScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene for PracticeScene {
+    fn update(&mut self, context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        self.poll(context)
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        draw_text(context, "Practice", 16.0, 16.0, TEXT_COLOR);
+        self.menu.draw(context);
+        draw_text(context, &self.status, 16.0, 128.0, TEXT_COLOR);
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        match keycode {
+            KeyCode::Escape => Transition::Pop(1),
+            _ => match self.menu.key_down(keycode) {
+                Some(index) => self.pick(index),
+                None => Transition::None,
+            },
+        }
+    }
+
+    fn mouse_down(
+        &mut self,
+        _context: &mut Context,
+        _campaign: &mut Campaign,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_down(x, y) {
+            Some(index) => self.pick(index),
+            None => Transition::None,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use ggez::{
+    event::KeyCode,
+    graphics::{self, Rect},
+    Context, GameResult,
+};
+use specs::WorldExt;
+use std::cmp::Ordering;
+
+use super::gameplay_scene::SEAT_KEYS;
+use super::level_world::LevelWorld;
+use super::{Scene, Transition};
+use crate::campaign::Campaign;
+use crate::constants::{MULTIPLIER, TEXT_PADDING, TEXT_SIZE, TILE_WIDTH};
+use crate::env::Action;
+use crate::map::Level;
+use crate::resources::*;
+use crate::ui::{draw_shade, draw_text, TEXT_COLOR};
+
+// Each racer takes back their own moves, player one with Q and player two
+// with U like a gamepad's west button
+const UNDO_KEYS: [KeyCode; 2] = [KeyCode::Q, KeyCode::U];
+
+// And switches between their own players on levels with several, player one
+// with E and player two with Tab like a gamepad's north button
+const SWITCH_KEYS: [KeyCode; 2] = [KeyCode::E, KeyCode::Tab];
+
+const NAMES: [&str; 2] = ["Left", "Right"];
+
+// One copy of the level with its own world, so the racers can't get in each
+// other's way
+struct Racer {
+    level_world: LevelWorld,
+}
+
+impl Racer {
+    fn new(context: &mut Context, level: &Level) -> Self {
+        let mut level_world = LevelWorld::new(context, level);
+        level_world.start();
+        Self { level_world }
+    }
+
+    // Draw the board and its counters into `area` of the screen, shrunk to
+    // fit if it has to be
+    fn draw(&mut self, context: &mut Context, area: Rect, name: &str) -> GameResult {
+        let width = self.level_world.cols as f32 * TILE_WIDTH * MULTIPLIER;
+        let height = (self.level_world.rows as f32 * TILE_WIDTH + (TEXT_SIZE + TEXT_PADDING) * 3.0)
+            * MULTIPLIER;
+        let scale = (area.w / width).min(area.h / height).min(1.0);
+        let left = area.x + (area.w - width * scale) / 2.0;
+
+        // Screen coordinates that put the board's origin at the top left of
+        // the area, at the scale it's drawn
+        let screen = graphics::screen_coordinates(context);
+        graphics::set_screen_coordinates(
+            context,
+            Rect::new(
+                -left / scale,
+                -area.y / scale,
+                screen.w / scale,
+                screen.h / scale,
+            ),
+        )?;
+        self.level_world.draw(context);
+        draw_text(
+            context,
+            name,
+            TEXT_PADDING,
+            TILE_WIDTH * self.level_world.rows as f32 + (TEXT_SIZE * 2.0) + TEXT_PADDING,
+            TEXT_COLOR,
+        );
+        graphics::set_screen_coordinates(context, screen)
+    }
+
+    fn press(&mut self, keycode: KeyCode) {
+        self.level_world
+            .world
+            .write_resource::<InputQueue>()
+            .keys_pressed
+            .push_back(keycode);
+    }
+
+    fn is_won(&self) -> bool {
+        matches!(
+            self.level_world.world.read_resource::<Gameplay>().state,
+            GameplayState::Won
+        )
+    }
+
+    // Fewer moves then fewer pushes is better
+    fn score(&self) -> (u32, u32) {
+        let gameplay = self.level_world.world.read_resource::<Gameplay>();
+        (gameplay.moves_count, gameplay.pushes_count)
+    }
+}
+
+enum Outcome {
+    Winner(usize),
+    Draw,
+}
+
+// Two people race through their own copies of the same level side by side,
+// the first to solve it wins. Solving it on the same update goes to whoever
+// used fewer moves, then fewer pushes.
+pub struct RaceScene {
+    racers: Vec<Racer>,
+    level: Level,
+    outcome: Option<Outcome>,
+}
+
+impl RaceScene {
+    pub fn new(context: &mut Context, level: Level) -> Self {
+        Self {
+            racers: NAMES.iter().map(|_| Racer::new(context, &level)).collect(),
+            level,
+            outcome: None,
+        }
+    }
+
+    // The racer and direction, undo or switch a key is for
+    fn racer_key(keycode: KeyCode) -> Option<(usize, KeyCode)> {
+        (0..NAMES.len()).find_map(|racer| {
+            if UNDO_KEYS[racer] == keycode {
+                return Some((racer, KeyCode::U));
+            }
+            if SWITCH_KEYS[racer] == keycode {
+                return Some((racer, KeyCode::Tab));
+            }
+            let direction = SEAT_KEYS[racer].iter().position(|key| *key == keycode)?;
+            Some((racer, Action::ALL[direction].key()))
+        })
+    }
+
+    fn finish(&self) -> Option<Outcome> {
+        let won: Vec<_> = (0..self.racers.len())
+            .filter(|racer| self.racers[*racer].is_won())
+            .collect();
+        match won[..] {
+            [] => None,
+            [racer] => Some(Outcome::Winner(racer)),
+            _ => {
+                let (first, second) = (won[0], won[1]);
+                match self.racers[first].score().cmp(&self.racers[second].score()) {
+                    Ordering::Less => Some(Outcome::Winner(first)),
+                    Ordering::Greater => Some(Outcome::Winner(second)),
+                    Ordering::Equal => Some(Outcome::Draw),
+                }
+            }
+        }
+    }
+}
+
+impl Scene for RaceScene {
+    fn update(&mut self, context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        // Nobody moves once the race is decided
+        if self.outcome.is_some() {
+            return Transition::None;
+        }
+
+        for racer in self.racers.iter_mut() {
+            racer.level_world.update(context);
+        }
+        self.outcome = self.finish();
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        let screen = graphics::screen_coordinates(context);
+        let width = screen.w / self.racers.len() as f32;
+        for (index, racer) in self.racers.iter_mut().enumerate() {
+            let area = Rect::new(screen.x + width * index as f32, screen.y, width, screen.h);
+            racer.draw(context, area, NAMES[index])?;
+        }
+
+        if let Some(outcome) = &self.outcome {
+            let text = match outcome {
+                Outcome::Winner(racer) => format!("{} wins!", NAMES[*racer]),
+                Outcome::Draw => "It's a draw!".to_string(),
+            };
+            draw_shade(context);
+            draw_text(context, &text, 16.0, 16.0, TEXT_COLOR);
+            for (index, racer) in self.racers.iter().enumerate() {
+                let (moves, pushes) = racer.score();
+                draw_text(
+                    context,
+                    &format!("{}: {} moves {} pushes", NAMES[index], moves, pushes),
+                    16.0,
+                    32.0 + 16.0 * index as f32,
+                    TEXT_COLOR,
+                );
+            }
+            draw_text(
+                context,
+                "Enter to race again, Esc to leave",
+                16.0,
+                32.0 + 16.0 * (self.racers.len() + 1) as f32,
+                TEXT_COLOR,
+            );
+        }
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        _campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        match keycode {
+            KeyCode::Escape => return Transition::Pop(1),
+            KeyCode::Return if self.outcome.is_some() => {
+                return Transition::Replace(
+                    1,
+                    Box::new(RaceScene::new(context, self.level.clone())),
+                )
+            }
+            _ => (),
+        }
+
+        if self.outcome.is_none() {
+            if let Some((racer, key)) = Self::racer_key(keycode) {
+                self.racers[racer].press(key);
+            }
+        }
+        Transition::None
+    }
+}
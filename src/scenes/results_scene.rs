@@ -0,0 +1,124 @@
+use ggez::{event::KeyCode, Context, GameResult};
+
+use super::{GameplayScene, MenuList, Scene, Transition};
+use crate::campaign::Campaign;
+use crate::map::Level;
+use crate::ui::{draw_shade, draw_text, TEXT_COLOR};
+
+const NEXT_LEVEL: &str = "Next level";
+const RETRY: &str = "Retry";
+const LEVEL_SELECT: &str = "Level select";
+const BACK: &str = "Back";
+
+// Drawn on top of a level once it has been won
+pub struct ResultsScene {
+    menu: MenuList,
+    level: Level,
+    level_index: Option<usize>,
+    // The next campaign level the same players can play
+    next_level: Option<usize>,
+    seats: usize,
+    moves_count: u32,
+    pushes_count: u32,
+}
+
+impl ResultsScene {
+    pub fn new(
+        campaign: &Campaign,
+        level: Level,
+        level_index: Option<usize>,
+        seats: usize,
+        moves_count: u32,
+        pushes_count: u32,
+    ) -> Self {
+        let next_level = level_index.and_then(|index| campaign.next_level(index, seats));
+        let items = match (level_index, next_level) {
+            (Some(_), Some(_)) => vec![NEXT_LEVEL, RETRY, LEVEL_SELECT],
+            (Some(_), None) => vec![RETRY, LEVEL_SELECT],
+            (None, _) => vec![RETRY, BACK],
+        };
+
+        Self {
+            menu: MenuList::new(items, 16.0, 64.0),
+            level,
+            level_index,
+            next_level,
+            seats,
+            moves_count,
+            pushes_count,
+        }
+    }
+
+    fn pick(&self, context: &mut Context, campaign: &Campaign, index: usize) -> Transition {
+        match self.menu.items[index] {
+            NEXT_LEVEL => Transition::Replace(
+                2,
+                Box::new(
+                    GameplayScene::from_campaign(context, campaign, self.next_level.unwrap_or(0))
+                        .with_seats(self.seats),
+                ),
+            ),
+            RETRY => Transition::Replace(
+                2,
+                Box::new(
+                    GameplayScene::new(context, self.level.clone(), self.level_index)
+                        .with_seats(self.seats),
+                ),
+            ),
+            LEVEL_SELECT | BACK => Transition::Pop(2),
+            _ => Transition::None,
+        }
+    }
+}
+
+impl Scene for ResultsScene {
+    fn update(&mut self, _context: &mut Context, _campaign: &mut Campaign) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut Context, _campaign: &Campaign) -> GameResult {
+        draw_shade(context);
+        draw_text(context, "Level solved!", 16.0, 16.0, TEXT_COLOR);
+        draw_text(
+            context,
+            &format!("Moves: {}  Pushes: {}", self.moves_count, self.pushes_count),
+            16.0,
+            32.0,
+            TEXT_COLOR,
+        );
+        self.menu.draw(context);
+        Ok(())
+    }
+
+    fn key_down(
+        &mut self,
+        context: &mut Context,
+        campaign: &mut Campaign,
+        keycode: KeyCode,
+    ) -> Transition {
+        match keycode {
+            KeyCode::Escape => Transition::Pop(2),
+            _ => match self.menu.key_down(keycode) {
+                Some(index) => self.pick(context, campaign, index),
+                None => Transition::None,
+            },
+        }
+    }
+
+    fn mouse_down(
+        &mut self,
+        context: &mut Context,
+        campaign: &mut Campaign,
+        x: f32,
+        y: f32,
+    ) -> Transition {
+        match self.menu.mouse_down(x, y) {
+            Some(index) => self.pick(context, campaign, index),
+            None => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,187 @@
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use image::{imageops, DynamicImage, ImageOutputFormat, ImageResult, Rgba, RgbaImage};
+use itertools::Itertools;
+use specs::{Join, World, WorldExt};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::components::*;
+use crate::constants::{TEXT_PADDING, TEXT_SIZE, TILE_WIDTH};
+use crate::resources::{Gameplay, Time};
+
+// Where the game loads images from when run from the repository
+pub const RESOURCES_DIR: &str = "resources";
+
+// The same grey the window is cleared to
+const BACKGROUND: Rgba<u8> = Rgba([242, 242, 242, 255]);
+const TEXT_COLOR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+// Draws worlds on the CPU with the same images and layering as the
+// `RenderingSystem`, so pictures can be taken without a window
+pub struct Screenshot {
+    // The directory images and the font are loaded from, like the game's
+    // resource directory
+    resources: PathBuf,
+    // How many pixels each image pixel becomes
+    scale: u32,
+    // Draw the state and move count under the board, like the game does
+    pub hud: bool,
+    images: HashMap<(String, u8), RgbaImage>,
+    font: Option<FontVec>,
+}
+
+impl Screenshot {
+    pub fn new(resources: &Path, scale: u32) -> Self {
+        Self {
+            resources: resources.to_path_buf(),
+            scale: scale.max(1),
+            hud: false,
+            images: HashMap::new(),
+            font: None,
+        }
+    }
+
+    pub fn render(&mut self, world: &World) -> RgbaImage {
+        let positions = world.read_storage::<Position>();
+        let renderables = world.read_storage::<Renderable>();
+        let players = world.read_storage::<Player>();
+        let actives = world.read_storage::<Active>();
+        let gameplay = world.read_resource::<Gameplay>();
+        let time = world.read_resource::<Time>();
+
+        let (cols, rows) = (&positions).join().fold((0, 0), |(cols, rows), p| {
+            (cols.max(p.x + 1), rows.max(p.y + 1))
+        });
+        let tile = TILE_WIDTH as u32 * self.scale;
+        let hud_height = if self.hud {
+            ((TEXT_SIZE + TEXT_PADDING) * 2.0) as u32 * self.scale
+        } else {
+            0
+        };
+        let mut canvas = RgbaImage::from_pixel(cols * tile, rows * tile + hud_height, BACKGROUND);
+
+        // Lower z first so things are layered the same as in the window
+        for (position, renderable) in (&positions, &renderables)
+            .join()
+            .sorted_by_key(|(position, _)| position.z)
+        {
+            let image = self.image(renderable, &time);
+            imageops::overlay(&mut canvas, &image, position.x * tile, position.y * tile);
+        }
+
+        // Mark which player moves when there's more than one
+        if (&players).join().count() > 1 {
+            for (position, _) in (&positions, &actives).join() {
+                let image = self.load(Active::IMAGE, 0);
+                imageops::overlay(&mut canvas, &image, position.x * tile, position.y * tile);
+            }
+        }
+
+        if self.hud {
+            let top = TILE_WIDTH * rows as f32;
+            self.draw_text(
+                &mut canvas,
+                &gameplay.state.to_string(),
+                TEXT_PADDING,
+                top + TEXT_PADDING,
+            );
+            self.draw_text(
+                &mut canvas,
+                &gameplay.moves_text(),
+                TEXT_PADDING,
+                top + TEXT_SIZE + TEXT_PADDING,
+            );
+        }
+
+        canvas
+    }
+
+    // Render and save to a PNG file
+    pub fn save(&mut self, world: &World, path: &Path) -> ImageResult<()> {
+        self.render(world).save(path)
+    }
+
+    // Render and encode as PNG, for writing somewhere other than a plain file
+    pub fn encode(&mut self, world: &World) -> ImageResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(self.render(world))
+            .write_to(&mut bytes, ImageOutputFormat::Png)?;
+        Ok(bytes)
+    }
+
+    // The scaled and turned image for the current frame, tinted if its color
+    // has no image of its own
+    fn image(&mut self, renderable: &Renderable, time: &Time) -> RgbaImage {
+        let path = renderable.frame(time.delta);
+        let turns = renderable.quarter_turns;
+        match &renderable.fallback {
+            Some(fallback) if !self.resource(&path).exists() => {
+                let mut image = self.load(&fallback.path, turns);
+                for pixel in image.pixels_mut() {
+                    for (channel, tint) in pixel.0.iter_mut().zip(fallback.rgb) {
+                        *channel = (*channel as u16 * tint as u16 / 255) as u8;
+                    }
+                }
+                image
+            }
+            _ => self.load(&path, turns),
+        }
+    }
+
+    fn load(&mut self, path: &str, quarter_turns: u8) -> RgbaImage {
+        let key = (path.to_string(), quarter_turns);
+        if !self.images.contains_key(&key) {
+            let image = image::open(self.resource(path))
+                .expect("expected image")
+                .to_rgba8();
+            let (width, height) = (image.width() * self.scale, image.height() * self.scale);
+            let mut image = imageops::resize(&image, width, height, imageops::FilterType::Nearest);
+            for _ in 0..quarter_turns {
+                image = imageops::rotate90(&image);
+            }
+            self.images.insert(key.clone(), image);
+        }
+        self.images[&key].clone()
+    }
+
+    // Game paths start from the resource directory with a slash
+    fn resource(&self, path: &str) -> PathBuf {
+        self.resources.join(path.trim_start_matches('/'))
+    }
+
+    // Coordinates are in unscaled pixels, like `ui::draw_text`
+    fn draw_text(&mut self, canvas: &mut RgbaImage, text: &str, x: f32, y: f32) {
+        if self.font.is_none() {
+            let data = fs::read(self.resource("/ARCADE_N.TTF")).expect("expected font");
+            self.font = Some(FontVec::try_from_vec(data).expect("expected a valid font"));
+        }
+        let font = self.font.as_ref().expect("expected font");
+        let scale = self.scale as f32;
+        let scaled = font.as_scaled(PxScale::from(TEXT_SIZE * scale));
+
+        let mut caret = x * scale;
+        for c in text.chars() {
+            let mut glyph = scaled.scaled_glyph(c);
+            glyph.position = ab_glyph::point(caret, y * scale + scaled.ascent());
+            caret += scaled.h_advance(glyph.id);
+
+            let outlined = match font.outline_glyph(glyph) {
+                Some(outlined) => outlined,
+                None => continue,
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= canvas.width() as i32 || py >= canvas.height() as i32 {
+                    return;
+                }
+                let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                for (channel, text) in pixel.0.iter_mut().zip(TEXT_COLOR.0).take(3) {
+                    *channel = (*channel as f32 * (1.0 - coverage) + text as f32 * coverage) as u8;
+                }
+            });
+        }
+    }
+}
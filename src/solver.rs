@@ -0,0 +1,662 @@
+use crate::components::{BoxColor, Gate};
+use crate::map::{Level, Tile};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Directions in LURD order, with the character used for moves. Pushes use
+// the upper case character.
+pub const DIRECTIONS: [(isize, isize, char); 4] =
+    [(-1, 0, 'l'), (0, -1, 'u'), (1, 0, 'r'), (0, 1, 'd')];
+
+// A level flattened into cells for searching. Cells are indexed `y * cols + x`.
+#[derive(Clone)]
+pub struct Board {
+    pub rows: usize,
+    pub cols: usize,
+    walls: Vec<bool>,
+    goals: Vec<Option<BoxColor>>,
+    // The other teleporter of a pair
+    exits: Vec<Option<usize>>,
+    ice: Vec<bool>,
+    gates: Vec<Option<Gate>>,
+    pits: Vec<bool>,
+    // Plates, switches and doors by their id. Switches only matter when
+    // there are doors.
+    plates: Vec<Option<char>>,
+    switches: Vec<Option<char>>,
+    doors: Vec<Option<char>>,
+    // Switches that start on
+    switches_on: Vec<usize>,
+    pub boxes: Vec<(usize, BoxColor)>,
+    pub player: usize,
+}
+
+impl Board {
+    // Returns None for levels without a player. Only the first player is
+    // kept, `solve` turns away levels with more.
+    pub fn from_level(level: &Level) -> Option<Self> {
+        let (rows, cols) = (level.rows(), level.cols());
+        let mut board = Self {
+            rows,
+            cols,
+            walls: vec![true; rows * cols],
+            goals: vec![None; rows * cols],
+            exits: vec![None; rows * cols],
+            ice: vec![false; rows * cols],
+            gates: vec![None; rows * cols],
+            pits: vec![false; rows * cols],
+            plates: vec![None; rows * cols],
+            switches: vec![None; rows * cols],
+            doors: vec![None; rows * cols],
+            switches_on: vec![],
+            boxes: vec![],
+            player: usize::MAX,
+        };
+
+        for (y, row) in level.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let cell = y * cols + x;
+                board.walls[cell] = matches!(tile, Tile::Wall | Tile::Nothing);
+                board.ice[cell] = tile.is_ice();
+                board.gates[cell] = tile.gate();
+                board.pits[cell] = *tile == Tile::Pit;
+                board.plates[cell] = tile.plate();
+                board.switches[cell] = tile.switch().map(|switch| switch.id);
+                board.doors[cell] = tile.door();
+                if tile.switch().is_some_and(|switch| switch.on) {
+                    board.switches_on.push(cell);
+                }
+                if tile.has_player() && board.player == usize::MAX {
+                    board.player = cell;
+                }
+                match *tile {
+                    Tile::Box(color) => board.boxes.push((cell, color)),
+                    Tile::BoxSpot(color) => board.goals[cell] = Some(color),
+                    Tile::BoxOnSpot(color, spot) => {
+                        board.boxes.push((cell, color));
+                        board.goals[cell] = Some(spot);
+                    }
+                    Tile::PlayerOnSpot(spot) => board.goals[cell] = Some(spot),
+                    Tile::BoxOnTeleporter(color, _) => board.boxes.push((cell, color)),
+                    Tile::BoxOnIce(color) | Tile::BoxOnGate(color, _) => {
+                        board.boxes.push((cell, color))
+                    }
+                    Tile::BoxOnPlate(color, _)
+                    | Tile::BoxOnSwitch(color, _)
+                    | Tile::BoxOnDoor(color, _) => board.boxes.push((cell, color)),
+                    _ => (),
+                }
+            }
+        }
+        for ((x, y), (exit_x, exit_y)) in level.teleporter_exits() {
+            board.exits[y * cols + x] = Some(exit_y * cols + exit_x);
+        }
+
+        if !board.has_doors() {
+            board.switches = vec![None; rows * cols];
+            board.switches_on.clear();
+        }
+
+        if board.player == usize::MAX {
+            return None;
+        }
+        Some(board)
+    }
+
+    // The cell next to `cell` in a direction, if it is on the board
+    pub fn step(&self, cell: usize, dx: isize, dy: isize) -> Option<usize> {
+        let x = (cell % self.cols) as isize + dx;
+        let y = (cell / self.cols) as isize + dy;
+        if x < 0 || y < 0 || x >= self.cols as isize || y >= self.rows as isize {
+            return None;
+        }
+        Some(y as usize * self.cols + x as usize)
+    }
+
+    // The cell next to `cell` in a direction, if it can be walked on
+    pub fn open(&self, cell: usize, dx: isize, dy: isize) -> Option<usize> {
+        self.step(cell, dx, dy).filter(|next| !self.walls[*next])
+    }
+
+    // Whether the player, or a box, can move onto `cell` going this way
+    fn can_enter(&self, cell: usize, dx: isize, dy: isize, is_player: bool) -> bool {
+        let direction = DIRECTIONS
+            .iter()
+            .position(|(x, y, _)| (*x, *y) == (dx, dy))
+            .expect("expected a direction");
+        self.gates[cell].is_none_or(|gate| gate.allows(direction, is_player))
+    }
+
+    // Where something stepping onto `cell` ends up. Teleporters lead to
+    // their partner unless something is on it.
+    fn enter(&self, cell: usize, occupied: impl Fn(usize) -> bool) -> usize {
+        match self.exits[cell] {
+            Some(exit) if !occupied(exit) && !self.walls[exit] => exit,
+            _ => cell,
+        }
+    }
+
+    // Where something moving onto `cell` stops. Ice keeps it going the same
+    // way until the next cell is a wall, `occupied` or the ice ends.
+    fn slide(
+        &self,
+        mut cell: usize,
+        (dx, dy): (isize, isize),
+        is_player: bool,
+        occupied: impl Fn(usize) -> bool,
+    ) -> usize {
+        while self.ice[cell] {
+            match self.open(cell, dx, dy) {
+                Some(next) if !occupied(next) && self.can_enter(next, dx, dy, is_player) => {
+                    cell = next
+                }
+                _ => break,
+            }
+        }
+        cell
+    }
+
+    // A pit no box has fallen into yet
+    fn is_open_pit(&self, cell: usize, changes: &Changes) -> bool {
+        self.pits[cell] && !changes.filled.contains(&cell)
+    }
+
+    // Whether the player can't step onto a cell, other than for a box on it
+    fn is_blocked(&self, cell: usize, changes: &Changes) -> bool {
+        self.is_open_pit(cell, changes) || changes.shut.contains(&cell)
+    }
+
+    fn has_doors(&self) -> bool {
+        self.doors.iter().any(Option::is_some)
+    }
+
+    // How things are at the start
+    fn start(&self) -> Changes {
+        let changes = Changes {
+            switches: self.switches_on.clone(),
+            ..Changes::default()
+        };
+        self.changed(changes, &[], self.player, &self.boxes)
+    }
+
+    // The changes after a step moved things from and to the cells in
+    // `moved`. Switches moved onto flip, like in the `InputSystem`, then doors
+    // open and shut like in the `DoorSystem`.
+    fn changed(
+        &self,
+        mut changes: Changes,
+        moved: &[(usize, usize)],
+        player: usize,
+        boxes: &[(usize, BoxColor)],
+    ) -> Changes {
+        for (from, to) in moved {
+            if from == to || self.switches[*to].is_none() {
+                continue;
+            }
+            match changes.switches.iter().position(|cell| cell == to) {
+                Some(index) => {
+                    changes.switches.remove(index);
+                }
+                None => {
+                    changes.switches.push(*to);
+                    changes.switches.sort();
+                }
+            }
+        }
+
+        let occupied = |cell: usize| cell == player || boxes.iter().any(|(b, _)| *b == cell);
+        let held_open: HashSet<char> = self
+            .plates
+            .iter()
+            .enumerate()
+            .filter(|(cell, _)| occupied(*cell))
+            .filter_map(|(_, id)| *id)
+            .chain(
+                changes
+                    .switches
+                    .iter()
+                    .filter_map(|cell| self.switches[*cell]),
+            )
+            .collect();
+        changes.shut = (0..self.doors.len())
+            .filter(|cell| {
+                self.doors[*cell].is_some_and(|id| !held_open.contains(&id)) && !occupied(*cell)
+            })
+            .collect();
+        changes
+    }
+
+    // Walking around and back gets the player to the same cells, which
+    // teleporters, ice and gates don't promise
+    fn is_reversible(&self) -> bool {
+        self.exits.iter().all(Option::is_none)
+            && !self.ice.contains(&true)
+            && self.gates.iter().all(Option::is_none)
+    }
+
+    // Where the box on `cell` and the player end up when the player pushes it
+    // from `from`, if it can be pushed. Things are moved the same way as the
+    // `InputSystem`, the box first. A box ending up on an open pit falls in.
+    fn push(
+        &self,
+        from: usize,
+        cell: usize,
+        boxes: &[(usize, BoxColor)],
+        changes: &Changes,
+    ) -> Option<(usize, usize)> {
+        let (dx, dy) = self.delta(from, cell);
+        let to = self.open(cell, dx, dy)?;
+        let has_box = |cell: usize| boxes.iter().any(|(b, _)| *b == cell);
+        let stops_box = |cell: usize| has_box(cell) || changes.shut.contains(&cell);
+        if stops_box(to)
+            || !self.can_enter(to, dx, dy, false)
+            || !self.can_enter(cell, dx, dy, true)
+        {
+            return None;
+        }
+
+        let pushed = self.slide(to, (dx, dy), false, stops_box);
+        let pushed = self.enter(pushed, |exit| exit == from || exit == cell || has_box(exit));
+        let player = self.slide(cell, (dx, dy), true, |next| {
+            next == pushed || has_box(next) || self.is_blocked(next, changes)
+        });
+        let player = self.enter(player, |exit| {
+            exit == pushed || exit == from || exit == cell || has_box(exit)
+        });
+        Some((pushed, player))
+    }
+
+    // Every goal has a box of a color it accepts on it
+    pub fn is_solved(&self, boxes: &[(usize, BoxColor)]) -> bool {
+        self.goals
+            .iter()
+            .enumerate()
+            .all(|(cell, goal)| match goal {
+                Some(goal) => boxes
+                    .iter()
+                    .any(|(b, color)| *b == cell && goal.accepts(*color)),
+                None => true,
+            })
+    }
+
+    // Cells a box of this color can never be pushed out of onto a goal of
+    // its color. Found by pulling boxes backwards away from every goal.
+    pub fn dead_squares(&self, color: BoxColor) -> Vec<bool> {
+        let mut alive = vec![false; self.walls.len()];
+        let mut queue: VecDeque<usize> = (0..self.goals.len())
+            .filter(|cell| self.goals[*cell].is_some_and(|goal| goal.accepts(color)))
+            .collect();
+        for cell in queue.iter() {
+            alive[*cell] = true;
+        }
+
+        // A box can land on a cell by being pushed onto it, or onto the
+        // teleporter it's the exit of, from the next cell or from further
+        // back if it slid over ice on the way. All of these are assumed to be
+        // possible, so only squares that are dead whatever is in the way are
+        // found. Gates that don't let a box in from that side are the
+        // exception.
+        while let Some(cell) = queue.pop_front() {
+            for entrance in [Some(cell), self.exits[cell]].into_iter().flatten() {
+                for (dx, dy, _) in DIRECTIONS {
+                    if !self.can_enter(entrance, -dx, -dy, false) {
+                        continue;
+                    }
+                    let mut pulled = self.open(entrance, dx, dy);
+                    while let Some(from) = pulled {
+                        if self.open(from, dx, dy).is_some() && !alive[from] {
+                            alive[from] = true;
+                            queue.push_back(from);
+                        }
+                        if !self.ice[from] {
+                            break;
+                        }
+                        pulled = self.open(from, dx, dy);
+                    }
+                }
+            }
+        }
+
+        alive.iter().map(|alive| !alive).collect()
+    }
+
+    // Where the player ends up stepping from `cell` without pushing
+    // anything, if it can move that way
+    fn walk_step(
+        &self,
+        cell: usize,
+        (dx, dy): (isize, isize),
+        blocked: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        let next = self
+            .open(cell, dx, dy)
+            .filter(|next| !blocked(*next) && self.can_enter(*next, dx, dy, true))?;
+        let next = self.slide(next, (dx, dy), true, &blocked);
+        Some(self.enter(next, |exit| exit == cell || blocked(exit)))
+    }
+
+    // Cells the player can walk to without pushing anything, with the cell
+    // each was first reached from and the move taken
+    fn reachable(
+        &self,
+        from: usize,
+        boxes: &[(usize, BoxColor)],
+        changes: &Changes,
+    ) -> Vec<Option<(usize, char)>> {
+        let blocked =
+            |cell: usize| boxes.iter().any(|(b, _)| *b == cell) || self.is_blocked(cell, changes);
+        let mut came_from = vec![None; self.walls.len()];
+        let mut queue = VecDeque::from(vec![from]);
+        came_from[from] = Some((from, ' '));
+
+        while let Some(cell) = queue.pop_front() {
+            for (dx, dy, c) in DIRECTIONS {
+                let next = match self.walk_step(cell, (dx, dy), blocked) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                if came_from[next].is_none() {
+                    came_from[next] = Some((cell, c));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        came_from
+    }
+
+    // The moves walking from one cell to another, in LURD notation
+    fn walk(
+        &self,
+        from: usize,
+        to: usize,
+        boxes: &[(usize, BoxColor)],
+        changes: &Changes,
+    ) -> String {
+        let came_from = self.reachable(from, boxes, changes);
+        let mut path = vec![];
+        let mut cell = to;
+        while cell != from {
+            let (previous, c) = came_from[cell].expect("expected reachable cell");
+            path.push(c);
+            cell = previous;
+        }
+
+        path.iter().rev().collect()
+    }
+
+    // The direction from a cell to a neighbouring one
+    fn delta(&self, from: usize, to: usize) -> (isize, isize) {
+        DIRECTIONS
+            .iter()
+            .find(|(dx, dy, _)| self.step(from, *dx, *dy) == Some(to))
+            .map(|(dx, dy, _)| (*dx, *dy))
+            .expect("expected neighbouring cells")
+    }
+}
+
+// What playing changes on a board besides where the player and boxes are
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+struct Changes {
+    // Pits boxes have fallen into, sorted
+    filled: Vec<usize>,
+    // Switches that are on, sorted
+    switches: Vec<usize>,
+    // Doors that are shut, worked out from the rest after each step
+    shut: Vec<usize>,
+}
+
+// A search node, the player position is normalized to the top left-most cell
+// it can reach so states that only differ by walking are the same. Boards
+// with teleporters or ice keep the player where it is, what it can reach
+// depends on where it starts.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct State {
+    player: usize,
+    boxes: Vec<(usize, BoxColor)>,
+    changes: Changes,
+}
+
+// A state with the node it was reached from, the cell the player pushed from
+// and the push direction
+type Node = (State, Option<(usize, usize, usize)>);
+
+// Find a solution with the fewest pushes, in LURD notation. Gives up after
+// looking at `max_states` positions. Solutions move one player, so levels
+// without exactly one are an error.
+pub fn solve(level: &Level, max_states: usize) -> Result<Option<String>, String> {
+    match level.player_count() {
+        0 => Err("the level has no player".to_string()),
+        1 => {
+            let board = Board::from_level(level).expect("expected a player");
+            Ok(search(board, max_states))
+        }
+        players => Err(format!(
+            "the solver only plays levels with one player, this one has {}",
+            players
+        )),
+    }
+}
+
+fn search(board: Board, max_states: usize) -> Option<String> {
+    let dead: HashMap<BoxColor, Vec<bool>> = board
+        .boxes
+        .iter()
+        .map(|(_, color)| (*color, board.dead_squares(*color)))
+        .collect();
+    if board.has_doors() {
+        return solve_steps(&board, &dead, max_states);
+    }
+
+    let reversible = board.is_reversible();
+    let normalize = |state: State| {
+        if !reversible {
+            return state;
+        }
+        let reachable = board.reachable(state.player, &state.boxes, &state.changes);
+        let player = reachable
+            .iter()
+            .position(|r| r.is_some())
+            .unwrap_or(state.player);
+        State { player, ..state }
+    };
+
+    let mut boxes = board.boxes.clone();
+    boxes.sort_by_key(|(cell, _)| *cell);
+    let start = normalize(State {
+        player: board.player,
+        boxes,
+        changes: board.start(),
+    });
+    let mut nodes: Vec<Node> = vec![(start.clone(), None)];
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from(vec![0]);
+
+    while let Some(index) = queue.pop_front() {
+        let state = nodes[index].0.clone();
+        if board.is_solved(&state.boxes) {
+            return Some(replay(&board, &dead, &nodes, index));
+        }
+
+        let reachable = board.reachable(state.player, &state.boxes, &state.changes);
+        for (i, (cell, _)) in state.boxes.iter().enumerate() {
+            for (direction, (dx, dy, _)) in DIRECTIONS.iter().enumerate() {
+                let from = match board.open(*cell, -dx, -dy) {
+                    Some(from) if reachable[from].is_some() => from,
+                    _ => continue,
+                };
+                let next = match push_box(&board, &dead, &state, i, from) {
+                    Some(next) => normalize(next),
+                    None => continue,
+                };
+                if seen.insert(next.clone()) {
+                    if seen.len() > max_states {
+                        return None;
+                    }
+                    nodes.push((next, Some((index, from, direction))));
+                    queue.push_back(nodes.len() - 1);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// Boards with doors are searched a step at a time, walking onto a plate or
+// switch can open or shut them. These find the fewest moves instead.
+fn solve_steps(
+    board: &Board,
+    dead: &HashMap<BoxColor, Vec<bool>>,
+    max_states: usize,
+) -> Option<String> {
+    let mut boxes = board.boxes.clone();
+    boxes.sort_by_key(|(cell, _)| *cell);
+    let start = State {
+        player: board.player,
+        boxes,
+        changes: board.start(),
+    };
+    let mut nodes: Vec<(State, Option<(usize, char)>)> = vec![(start.clone(), None)];
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from(vec![0]);
+
+    while let Some(index) = queue.pop_front() {
+        let state = nodes[index].0.clone();
+        if board.is_solved(&state.boxes) {
+            let mut moves = vec![];
+            let mut current = index;
+            while let Some((parent, c)) = nodes[current].1 {
+                moves.push(c);
+                current = parent;
+            }
+            return Some(moves.iter().rev().collect());
+        }
+
+        let has_box = |cell: usize| state.boxes.iter().position(|(b, _)| *b == cell);
+        for (dx, dy, c) in DIRECTIONS {
+            let pushed = board.open(state.player, dx, dy).and_then(&has_box);
+            let next = match pushed {
+                Some(i) => push_box(board, dead, &state, i, state.player)
+                    .map(|next| (next, c.to_ascii_uppercase())),
+                None => board
+                    .walk_step(state.player, (dx, dy), |cell| {
+                        has_box(cell).is_some() || board.is_blocked(cell, &state.changes)
+                    })
+                    .map(|player| {
+                        let changes = board.changed(
+                            state.changes.clone(),
+                            &[(state.player, player)],
+                            player,
+                            &state.boxes,
+                        );
+                        let boxes = state.boxes.clone();
+                        let next = State {
+                            player,
+                            boxes,
+                            changes,
+                        };
+                        (next, c)
+                    }),
+            };
+
+            if let Some((next, c)) = next {
+                if seen.insert(next.clone()) {
+                    if seen.len() > max_states {
+                        return None;
+                    }
+                    nodes.push((next, Some((index, c))));
+                    queue.push_back(nodes.len() - 1);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// The state after the player on `from` pushes box `i`, unless it can't be
+// pushed or it can't get onto a goal any more
+fn push_box(
+    board: &Board,
+    dead: &HashMap<BoxColor, Vec<bool>>,
+    state: &State,
+    i: usize,
+    from: usize,
+) -> Option<State> {
+    let (cell, color) = state.boxes[i];
+    let (to, player) = board.push(from, cell, &state.boxes, &state.changes)?;
+
+    // A box that falls into a pit is gone, there have to be enough left for
+    // the goals
+    let mut boxes = state.boxes.clone();
+    let mut changes = state.changes.clone();
+    let goals = board.goals.iter().flatten().count();
+    if board.is_open_pit(to, &changes) {
+        if boxes.len() <= goals {
+            return None;
+        }
+        boxes.remove(i);
+        changes.filled.push(to);
+        changes.filled.sort();
+    } else {
+        // Boxes the goals don't need can be left anywhere, like holding a
+        // plate down, but no more of them than there are to spare
+        let stuck = state
+            .boxes
+            .iter()
+            .enumerate()
+            .filter(|(j, (cell, color))| *j != i && dead[color][*cell])
+            .count();
+        if dead[&color][to] && stuck >= boxes.len().saturating_sub(goals) {
+            return None;
+        }
+        boxes[i].0 = to;
+        boxes.sort_by_key(|(cell, _)| *cell);
+    }
+
+    let changes = board.changed(changes, &[(from, player), (cell, to)], player, &boxes);
+    Some(State {
+        player,
+        boxes,
+        changes,
+    })
+}
+
+// Turn the chain of pushes leading to a node into moves
+fn replay(
+    board: &Board,
+    dead: &HashMap<BoxColor, Vec<bool>>,
+    nodes: &[Node],
+    index: usize,
+) -> String {
+    let mut pushes = vec![];
+    let mut current = index;
+    while let Some((parent, from, direction)) = nodes[current].1 {
+        pushes.push((from, direction));
+        current = parent;
+    }
+
+    let mut boxes = board.boxes.clone();
+    boxes.sort_by_key(|(cell, _)| *cell);
+    let mut state = State {
+        player: board.player,
+        boxes,
+        changes: board.start(),
+    };
+    let mut moves = String::new();
+    for (from, direction) in pushes.into_iter().rev() {
+        let (dx, dy, c) = DIRECTIONS[direction];
+        moves.push_str(&board.walk(state.player, from, &state.boxes, &state.changes));
+
+        let pushed = board.step(from, dx, dy).expect("expected box");
+        let i = state
+            .boxes
+            .iter()
+            .position(|(cell, _)| *cell == pushed)
+            .expect("expected box");
+        state = push_box(board, dead, &state, i, from).expect("expected box target");
+        moves.push(c.to_ascii_uppercase());
+    }
+
+    moves
+}
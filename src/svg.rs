@@ -0,0 +1,346 @@
+use specs::{shrev::EventChannel, Entity, Join, WorldExt};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::components::{Active, BoxColor, Coord, Position};
+use crate::env::{Action, RewardConfig, SokobanEnv};
+use crate::events::{Event, Teleported};
+use crate::map::{Level, Tile};
+
+const CELL: usize = 32;
+// Room around the board for coordinates
+const MARGIN: usize = 20;
+
+const WALL_FILL: &str = "#505050";
+const FLOOR_FILL: &str = "#e6e6e6";
+const ICE_FILL: &str = "#c8e6f5";
+const OUTLINE: &str = "#303030";
+const PATH_STROKE: &str = "#202020";
+const TELEPORTER_STROKE: &str = "#8040c0";
+const GATE_FILL: &str = "#707070";
+const PIT_FILL: &str = "#1a1a1a";
+const MECHANISM_STROKE: &str = "#a06020";
+
+// What to draw on top of the level itself
+#[derive(Debug, Clone, Default)]
+pub struct SvgOptions {
+    // Column numbers across the top and row numbers down the side, from 0
+    pub coordinates: bool,
+    // A solution in LURD notation, drawn as the path the player walks with
+    // pushes marked
+    pub solution: Option<String>,
+}
+
+// Draw a level as an SVG document. Levels from a world being played can be
+// drawn by taking a `map::snapshot` first.
+pub fn level_svg(level: &Level, options: &SvgOptions) -> String {
+    let (rows, cols) = (level.rows(), level.cols());
+    let margin = if options.coordinates { MARGIN } else { 0 };
+    let (width, height) = (cols * CELL + margin, rows * CELL + margin);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(svg, r#"<g transform="translate({m} {m})">"#, m = margin);
+
+    let held_open = level.held_open();
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            draw_tile(&mut svg, level, &held_open, *tile, x, y);
+        }
+    }
+    if let Some(solution) = &options.solution {
+        draw_path(&mut svg, level, solution);
+    }
+
+    let _ = writeln!(svg, "</g>");
+    if options.coordinates {
+        draw_coordinates(&mut svg, rows, cols);
+    }
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+fn draw_tile(
+    svg: &mut String,
+    level: &Level,
+    held_open: &HashSet<char>,
+    tile: Tile,
+    x: usize,
+    y: usize,
+) {
+    let (left, top) = (x * CELL, y * CELL);
+    let mut rect = |fill: &str| {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{c}" height="{c}" fill="{}"/>"#,
+            left,
+            top,
+            fill,
+            c = CELL
+        );
+    };
+    match tile {
+        Tile::Nothing => return,
+        Tile::Wall => {
+            rect(WALL_FILL);
+            return;
+        }
+        Tile::Pit => {
+            rect(PIT_FILL);
+            return;
+        }
+        _ if tile.is_ice() => rect(ICE_FILL),
+        _ => rect(FLOOR_FILL),
+    }
+
+    // Teleporters and spots under whatever stands on them
+    if let Some(id) = tile.teleporter() {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{cx}" cy="{cy}" r="{}" fill="none" stroke="{}" stroke-width="2" stroke-dasharray="4 2"/>"#,
+            CELL / 2 - 3,
+            TELEPORTER_STROKE,
+            cx = left + CELL / 2,
+            cy = top + CELL / 2
+        );
+        draw_id(svg, left, top, TELEPORTER_STROKE, id);
+    }
+    // Plates are squares and switches circles, filled when on. Doors are
+    // filled when shut, something in one holds it open.
+    if let Some(id) = tile.plate() {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            left + 5,
+            top + 5,
+            MECHANISM_STROKE,
+            s = CELL - 10
+        );
+        draw_id(svg, left, top, MECHANISM_STROKE, id);
+    }
+    if let Some(switch) = tile.switch() {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="{}" stroke-width="2"/>"#,
+            left + CELL / 2,
+            top + CELL / 2,
+            CELL / 4,
+            if switch.on { MECHANISM_STROKE } else { "none" },
+            MECHANISM_STROKE
+        );
+        draw_id(svg, left, top, MECHANISM_STROKE, switch.id);
+    }
+    if let Some(id) = tile.door() {
+        let open = held_open.contains(&id) || tile != Tile::Door(id);
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}" stroke="{}" stroke-width="2"{}/>"#,
+            left + 1,
+            top + 1,
+            if open { "none" } else { MECHANISM_STROKE },
+            MECHANISM_STROKE,
+            if open {
+                r#" stroke-dasharray="4 2""#
+            } else {
+                ""
+            },
+            s = CELL - 2
+        );
+        draw_id(
+            svg,
+            left,
+            top,
+            if open { MECHANISM_STROKE } else { FLOOR_FILL },
+            id,
+        );
+    }
+    // An arrow for each way into a gate, drawn pointing up and turned
+    for direction in tile
+        .gate()
+        .iter()
+        .flat_map(|gate| (0..4).filter(move |direction| gate.directions[*direction]))
+    {
+        let (cx, cy) = (left + CELL / 2, top + CELL / 2);
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{},{} {},{} {},{}" fill="{}" transform="rotate({} {} {})"/>"#,
+            cx,
+            top + 3,
+            cx - 5,
+            top + 10,
+            cx + 5,
+            top + 10,
+            GATE_FILL,
+            (direction + 3) % 4 * 90,
+            cx,
+            cy
+        );
+    }
+    if let Tile::BoxSpot(spot) | Tile::BoxOnSpot(_, spot) | Tile::PlayerOnSpot(spot) = tile {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-width="3"/>"#,
+            left + CELL / 2,
+            top + CELL / 2,
+            CELL / 4,
+            fill(level, spot)
+        );
+    }
+    if let Some(color) = tile.box_color() {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{s}" height="{s}" rx="3" fill="{}" fill-opacity="0.85" stroke="{}" stroke-width="2"/>"#,
+            left + 4,
+            top + 4,
+            fill(level, color),
+            OUTLINE,
+            s = CELL - 8
+        );
+    }
+    if tile.has_player() {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            left + CELL / 2,
+            top + CELL / 2,
+            CELL / 3,
+            OUTLINE
+        );
+    }
+}
+
+// The id linking a teleporter, plate, switch or door, in the top left corner
+fn draw_id(svg: &mut String, left: usize, top: usize, color: &str, id: char) {
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" font-family="monospace" font-size="8" fill="{}">{}</text>"#,
+        left + 2,
+        top + 9,
+        color,
+        escape(id)
+    );
+}
+
+// Ids can be any character, including ones that mean something in XML
+fn escape(c: char) -> String {
+    match c {
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '&' => "&amp;".to_string(),
+        c => c.to_string(),
+    }
+}
+
+fn fill(level: &Level, color: BoxColor) -> String {
+    let [r, g, b] = level.palette.get(color).rgb;
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// The cells the player walks through as lines, with a dot where each push
+// ends. The solution is played with the game's rules so teleporters and ice
+// are followed. Each teleport starts a new line, slides carry straight on.
+// Characters that aren't moves are skipped.
+fn draw_path(svg: &mut String, level: &Level, solution: &str) {
+    let config = RewardConfig {
+        max_steps: None,
+        terminate_on_deadlock: false,
+        ..RewardConfig::default()
+    };
+    let mut env = SokobanEnv::new(config, vec![]);
+    if env.reset(level).is_err() {
+        return;
+    }
+
+    let center =
+        |(x, y): (Coord, Coord)| (x as usize * CELL + CELL / 2, y as usize * CELL + CELL / 2);
+    let mut events = env
+        .world()
+        .write_resource::<EventChannel<Event>>()
+        .register_reader();
+    let (_, position) = active_player(&env);
+    let mut lines = vec![vec![center(position)]];
+    let mut pushes = vec![];
+    for c in solution.chars() {
+        let action = match Action::from_lurd(c) {
+            Some(action) => action,
+            None => continue,
+        };
+        env.step(action);
+
+        let (player, position) = active_player(&env);
+        let entrance = env
+            .world()
+            .read_resource::<EventChannel<Event>>()
+            .read(&mut events)
+            .find_map(|event| match event {
+                Event::Teleported(Teleported {
+                    entity, entrance, ..
+                }) if *entity == player => Some(*entrance),
+                _ => None,
+            });
+        if let Some(entrance) = entrance {
+            lines
+                .last_mut()
+                .expect("expected a line")
+                .push(center((entrance.x, entrance.y)));
+            lines.push(vec![]);
+        }
+        lines
+            .last_mut()
+            .expect("expected a line")
+            .push(center(position));
+        if c.is_ascii_uppercase() {
+            pushes.push(center(position));
+        }
+    }
+
+    for line in lines {
+        let points: Vec<String> = line.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2" stroke-opacity="0.6" stroke-linejoin="round"/>"#,
+            points.join(" "),
+            PATH_STROKE
+        );
+    }
+    for (x, y) in pushes {
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="3" fill="{}"/>"#,
+            x, y, PATH_STROKE
+        );
+    }
+}
+
+fn active_player(env: &SokobanEnv) -> (Entity, (Coord, Coord)) {
+    let world = env.world();
+    let entities = world.entities();
+    let positions = world.read_storage::<Position>();
+    let actives = world.read_storage::<Active>();
+    (&entities, &positions, &actives)
+        .join()
+        .next()
+        .map(|(entity, position, _)| (entity, (position.x, position.y)))
+        .expect("expected an active player")
+}
+
+fn draw_coordinates(svg: &mut String, rows: usize, cols: usize) {
+    let text = |svg: &mut String, x: usize, y: usize, label: usize| {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="monospace" font-size="10" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+            x, y, label
+        );
+    };
+    for x in 0..cols {
+        text(svg, MARGIN + x * CELL + CELL / 2, MARGIN / 2, x);
+    }
+    for y in 0..rows {
+        text(svg, MARGIN / 2, MARGIN + y * CELL + CELL / 2, y);
+    }
+}
@@ -0,0 +1,79 @@
+use specs::{shrev::EventChannel, Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
+use std::collections::HashSet;
+
+use crate::{
+    components::{Door, Immovable, Movable, Plate, Position, Renderable, Switch},
+    events::{DoorToggled, Event},
+    resources::Grid,
+};
+
+// Opens and shuts doors after things have moved, from the plates and
+// switches with the same id
+pub struct DoorSystem {}
+
+impl<'a> System<'a> for DoorSystem {
+    // Data
+    type SystemData = (
+        Write<'a, EventChannel<Event>>,
+        Read<'a, Grid>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Plate>,
+        ReadStorage<'a, Switch>,
+        ReadStorage<'a, Movable>,
+        WriteStorage<'a, Door>,
+        WriteStorage<'a, Immovable>,
+        WriteStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut events,
+            grid,
+            entities,
+            positions,
+            plates,
+            switches,
+            movables,
+            mut doors,
+            mut immovables,
+            mut renderables,
+        ) = data;
+        let occupied = |position: &Position| {
+            grid.at(position.x, position.y)
+                .iter()
+                .any(|entity| movables.contains(*entity))
+        };
+
+        let held_open: HashSet<char> = (&plates, &positions)
+            .join()
+            .filter(|(_, position)| occupied(position))
+            .map(|(plate, _)| plate.id)
+            .chain(
+                (&switches)
+                    .join()
+                    .filter(|switch| switch.on)
+                    .map(|switch| switch.id),
+            )
+            .collect();
+
+        for (entity, door, position) in (&entities, &mut doors, &positions).join() {
+            // Whatever is in a door keeps it from shutting
+            let open = held_open.contains(&door.id) || occupied(position);
+            if open == door.open {
+                continue;
+            }
+
+            door.open = open;
+            if open {
+                immovables.remove(entity);
+            } else {
+                let _ = immovables.insert(entity, Immovable);
+            }
+            if let Some(renderable) = renderables.get_mut(entity) {
+                *renderable = Renderable::new_static(door.image());
+            }
+            events.single_write(Event::DoorToggled(DoorToggled { entity, open }));
+        }
+    }
+}
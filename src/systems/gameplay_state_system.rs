@@ -1,9 +1,9 @@
-use specs::{Join, ReadStorage, System, Write};
-use std::collections::HashMap;
+use specs::{shrev::EventChannel, Entities, Join, Read, ReadStorage, System, Write};
 
 use crate::{
-    components::{Box, BoxColor, BoxSpot, Position},
-    resources::{Gameplay, GameplayState},
+    components::{Box, BoxSpot, Position},
+    events::Event,
+    resources::{Gameplay, GameplayState, Grid},
 };
 
 pub struct GameplayStateSystem {}
@@ -12,42 +12,48 @@ impl<'a> System<'a> for GameplayStateSystem {
     // Data
     type SystemData = (
         Write<'a, Gameplay>,
+        Write<'a, EventChannel<Event>>,
+        Read<'a, Grid>,
+        Entities<'a>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Box>,
         ReadStorage<'a, BoxSpot>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut gameplay_state, positions, boxes, box_spots) = data;
+        let (mut gameplay_state, mut events, grid, entities, positions, boxes, box_spots) = data;
 
-        // get all boxes indexed by position
-        let boxes_by_position: HashMap<(u8, u8), &Box> = (&positions, &boxes)
+        // Boxes that fell into pits are off the grid, so they don't count.
+        // With fewer left than goals the level can't be finished.
+        let was_won = matches!(gameplay_state.state, GameplayState::Won);
+        let box_count = (&entities, &boxes, &positions)
             .join()
-            .map(|t| ((t.0.x, t.0.y), t.1))
-            .collect::<HashMap<_, _>>();
-        let mut boxes_by_color: HashMap<BoxColor, HashMap<(u8, u8), &Box>> = HashMap::new();
-        for (pos, _box) in boxes_by_position.iter() {
-            boxes_by_color
-                .entry(_box.color)
-                .or_default()
-                .insert((pos.0, pos.1), _box);
-        }
+            .filter(|(entity, _, position)| grid.at(position.x, position.y).contains(entity))
+            .count();
+        gameplay_state.state = if box_count < (&box_spots).join().count() {
+            GameplayState::Stuck
+        } else {
+            GameplayState::Playing
+        };
 
-        // loop through all box spots and check if there is a corresponding
-        // box at that position
-        gameplay_state.state = GameplayState::Playing;
-        for (_box_spot, position) in (&box_spots, &positions).join() {
-            if !boxes_by_color.contains_key(&_box_spot.color) {
+        // loop through all box spots and check if there is a box of a color
+        // the spot accepts at that position
+        for (box_spot, position) in (&box_spots, &positions).join() {
+            let has_box = grid
+                .at(position.x, position.y)
+                .iter()
+                .filter_map(|entity| boxes.get(*entity))
+                .any(|the_box| box_spot.color.accepts(the_box.color));
+            if !has_box {
                 return;
             }
-            if boxes_by_color[&_box_spot.color].contains_key(&(position.x, position.y)) {
-                continue;
-            }
-            return;
         }
 
         // If we made it this far, then all box spots have boxes on them, and the
         // game has been won
         gameplay_state.state = GameplayState::Won;
+        if !was_won {
+            events.single_write(Event::LevelCompleted);
+        }
     }
 }
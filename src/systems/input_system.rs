@@ -1,115 +1,480 @@
 use crate::components::*;
-use crate::events::{EntityMoved, Event};
-use crate::resources::{EventQueue, Gameplay, InputQueue};
+use crate::events::{
+    BoxFellInPit, BoxPlacedOnSpot, BoxPushed, BoxRemovedFromSpot, EntityMoved, Event, PlayerMoved,
+    Teleported, Undo,
+};
+use crate::resources::{Grid, History, InputQueue, Step, Swallowed};
 use ggez::event::KeyCode;
-use specs::{world::Index, Entities, Join, ReadStorage, System, Write, WriteStorage};
-use std::collections::HashMap;
+use specs::{
+    shrev::EventChannel, Entities, Entity, Join, ReadStorage, System, Write, WriteStorage,
+};
 
 pub struct InputSystem {
-    pub cols: u8,
-    pub rows: u8,
+    pub cols: Coord,
+    pub rows: Coord,
 }
 
 impl<'a> System<'a> for InputSystem {
     // Data
     type SystemData = (
-        Write<'a, EventQueue>,
+        Write<'a, EventChannel<Event>>,
         Write<'a, InputQueue>,
-        Write<'a, Gameplay>,
+        Write<'a, History>,
+        Write<'a, Grid>,
         Entities<'a>,
         WriteStorage<'a, Position>,
+        WriteStorage<'a, Renderable>,
         ReadStorage<'a, Player>,
-        ReadStorage<'a, Movable>,
+        WriteStorage<'a, Active>,
+        ReadStorage<'a, Seat>,
+        WriteStorage<'a, Box>,
+        ReadStorage<'a, BoxSpot>,
+        ReadStorage<'a, Teleporter>,
+        ReadStorage<'a, Ice>,
+        ReadStorage<'a, Gate>,
+        WriteStorage<'a, Pit>,
+        WriteStorage<'a, Switch>,
+        WriteStorage<'a, Movable>,
         ReadStorage<'a, Immovable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            mut event_queue,
+            mut events,
             mut input_queue,
-            mut gameplay,
+            mut history,
+            mut grid,
             entities,
             mut positions,
+            mut renderables,
             players,
-            movables,
+            mut actives,
+            seats,
+            mut boxes,
+            box_spots,
+            teleporters,
+            ices,
+            gates,
+            mut pits,
+            mut switches,
+            mut movables,
             immovables,
         ) = data;
-        let mut to_move = Vec::new();
+        let mut undo = false;
+        let mut turns = Vec::new();
 
-        for (position, _player) in (&positions, &players).join() {
-            // Get the first key pressed
-            if let Some(key) = input_queue.keys_pressed.pop() {
-                let mov: HashMap<(u8, u8), Index> = (&entities, &movables, &positions)
+        // Get the first key pressed
+        match input_queue.keys_pressed.pop_front() {
+            Some(KeyCode::U) => undo = true,
+            // Tab hands control to the next player in reading order
+            Some(KeyCode::Tab) => {
+                let mut order: Vec<_> = (&entities, &positions, &players)
                     .join()
-                    .map(|t| ((t.2.x, t.2.y), t.0.id()))
-                    .collect::<HashMap<_, _>>();
-                let immov: HashMap<(u8, u8), Index> = (&entities, &immovables, &positions)
-                    .join()
-                    .map(|t| ((t.2.x, t.2.y), t.0.id()))
-                    .collect::<HashMap<_, _>>();
-
-                // Now iterate through current position to the end of the map
-                // on the correct axis and check what needs to move.
-                let (start, end, is_x) = match key {
-                    KeyCode::Up => (position.y, 0, false),
-                    KeyCode::Down => (position.y, self.rows, false),
-                    KeyCode::Left => (position.x, 0, true),
-                    KeyCode::Right => (position.x, self.cols, true),
-                    _ => continue,
-                };
-                let range = if start < end {
-                    (start..=end).collect::<Vec<_>>()
+                    .map(|(entity, position, _)| ((position.y, position.x), entity))
+                    .collect();
+                order.sort();
+                let current = order
+                    .iter()
+                    .position(|(_, entity)| actives.contains(*entity));
+                if let Some(current) = current {
+                    let (_, next) = order[(current + 1) % order.len()];
+                    actives.clear();
+                    actives
+                        .insert(next, Active)
+                        .expect("expected to mark the player active");
+                }
+            }
+            Some(key) => {
+                if let Some((entity, _, _)) = (&entities, &players, &actives).join().next() {
+                    turns.push((entity, key));
+                }
+            }
+            None => (),
+        }
+
+        // Each co-op player takes their oldest move. Player one goes first,
+        // so when both go for the same cell they get it and player two bumps
+        // into them.
+        let mut seated: Vec<_> = (&entities, &seats)
+            .join()
+            .map(|(entity, seat)| (seat.0, entity))
+            .collect();
+        seated.sort();
+        for (seat, entity) in seated.iter() {
+            let queued = input_queue
+                .seat_keys
+                .iter()
+                .position(|(queued, _)| queued == seat);
+            if let Some(index) = queued {
+                let (_, key) = input_queue.seat_keys.remove(index);
+                turns.push((*entity, key));
+            }
+        }
+        // Nobody sits in a seat without a player, its keys would wait forever
+        input_queue
+            .seat_keys
+            .retain(|(queued, _)| seated.iter().any(|(seat, _)| seat == queued));
+
+        // Put everything the last step moved back where it was, and boxes
+        // that fell into pits back out
+        if undo {
+            if let Some(step) = history.steps.pop() {
+                for (entity, previous) in step.moved.iter() {
+                    if let Some(position) = positions.get_mut(*entity) {
+                        grid.move_entity(*entity, position, previous);
+                        *position = *previous;
+                    }
+                }
+                for swallowed in step.swallowed.iter() {
+                    if let Some(pit) = pits.get_mut(swallowed.pit) {
+                        pit.filled = false;
+                        if let Some(renderable) = renderables.get_mut(swallowed.pit) {
+                            *renderable = Renderable::new_static(pit.image());
+                        }
+                    }
+                    let entity = entities
+                        .build_entity()
+                        .with(swallowed.position, &mut positions)
+                        .with(swallowed.renderable.clone(), &mut renderables)
+                        .with(
+                            Box {
+                                color: swallowed.color,
+                            },
+                            &mut boxes,
+                        )
+                        .with(Movable, &mut movables)
+                        .build();
+                    grid.insert(entity, &swallowed.position);
+
+                    // Earlier steps know the box by the entity that was deleted
+                    for (moved, _) in history
+                        .steps
+                        .iter_mut()
+                        .flat_map(|step| step.moved.iter_mut())
+                    {
+                        if *moved == swallowed.entity {
+                            *moved = entity;
+                        }
+                    }
+                }
+                for entity in step.flipped.iter() {
+                    flip(*entity, &mut switches, &mut renderables);
+                }
+                events.single_write(Event::Undo(Undo {
+                    pushes: step.pushes,
+                    player: step.player,
+                }));
+            }
+        }
+
+        // Moves are made one after the other, each sees where the ones
+        // before left things
+        for (player, key) in turns {
+            let position = match positions.get(player) {
+                Some(position) => *position,
+                None => continue,
+            };
+            let mut to_move = Vec::new();
+
+            // Now iterate through current position to the end of the map
+            // on the correct axis and check what needs to move.
+            let (start, end, is_x) = match key {
+                KeyCode::Up => (position.y, 0, false),
+                KeyCode::Down => (position.y, self.rows.saturating_sub(1), false),
+                KeyCode::Left => (position.x, 0, true),
+                KeyCode::Right => (position.x, self.cols.saturating_sub(1), true),
+                _ => continue,
+            };
+            // Walked lazily, the far edge of a huge map is never reached
+            let range: std::boxed::Box<dyn Iterator<Item = Coord>> = if start < end {
+                std::boxed::Box::new(start..=end)
+            } else {
+                std::boxed::Box::new((end..=start).rev())
+            };
+
+            let mut found_gap = false;
+            for x_or_y in range {
+                let (x, y) = if is_x {
+                    (x_or_y, position.y)
                 } else {
-                    (end..=start).rev().collect::<Vec<_>>()
+                    (position.x, x_or_y)
                 };
+                let occupants = grid.at(x, y);
 
-                for x_or_y in range {
-                    let pos = if is_x {
-                        (x_or_y, position.y)
+                // A gate stops whatever would be moved onto it the wrong
+                // way, like a wall
+                let entering = to_move.last().map(|(_, entity)| *entity);
+                let gate = occupants.iter().find_map(|entity| gates.get(*entity));
+                if let (Some(entering), Some(gate)) = (entering, gate) {
+                    if !gate.allows(direction(key), players.contains(entering)) {
+                        to_move.clear();
+                        events.single_write(Event::PlayerHitObstacle);
+                        break;
+                    }
+                }
+
+                // An open pit can't be walked into, a box pushed into
+                // it falls in
+                let open_pit = occupants
+                    .iter()
+                    .filter_map(|entity| pits.get(*entity))
+                    .any(|pit| !pit.filled);
+                if let (Some(entering), true) = (entering, open_pit) {
+                    if players.contains(entering) {
+                        to_move.clear();
+                        events.single_write(Event::PlayerHitObstacle);
                     } else {
-                        (position.x, x_or_y)
-                    };
+                        found_gap = true;
+                    }
+                    break;
+                }
 
-                    // find a movable
-                    // if it exists, we try to move it and continue
-                    // if it doesn't exist, we continue and try to find an immovable instead
-                    match mov.get(&pos) {
-                        Some(id) => to_move.push((key, id.clone())),
-                        None => {
-                            // find an immovable
-                            // if it exists, we need to stop and not move anything
-                            // if it doesn't exist, we stop because we found a gap
-                            match immov.get(&pos) {
-                                Some(_id) => {
-                                    to_move.clear();
-                                    event_queue.events.push(Event::PlayerHitObstacle)
-                                }
-                                None => break,
+                // find a movable
+                // if it exists, we try to move it and continue
+                // if it doesn't exist, we continue and try to find an immovable instead
+                match occupants.iter().find(|entity| movables.contains(**entity)) {
+                    // Players don't push each other, the other one is in
+                    // the way
+                    Some(entity) if players.contains(*entity) && !to_move.is_empty() => {
+                        to_move.clear();
+                        events.single_write(Event::PlayerHitObstacle);
+                        break;
+                    }
+                    Some(entity) => to_move.push((key, *entity)),
+                    None => {
+                        // find an immovable
+                        // if it exists, we need to stop and not move anything
+                        // if it doesn't exist, we stop because we found a gap
+                        match occupants
+                            .iter()
+                            .find(|entity| immovables.contains(**entity))
+                        {
+                            Some(_) => {
+                                to_move.clear();
+                                events.single_write(Event::PlayerHitObstacle);
+                                break;
+                            }
+                            None => {
+                                found_gap = true;
+                                break;
                             }
                         }
                     }
                 }
             }
-        }
-        // Now actually move what needs to be moved
-        if to_move.len() > 0 {
-            gameplay.moves_count += 1;
-        }
-        for (key, id) in to_move {
-            let position = positions.get_mut(entities.entity(id));
-            if let Some(position) = position {
-                match key {
-                    KeyCode::Up => position.y -= 1,
-                    KeyCode::Down => position.y += 1,
-                    KeyCode::Left => position.x -= 1,
-                    KeyCode::Right => position.x += 1,
-                    _ => (),
+
+            // The edge of the map blocks like a wall, nothing can be
+            // pushed off it
+            if !found_gap {
+                to_move.clear();
+            }
+
+            // Work out where everything ends up, farthest from the player first
+            // so a cell taken by something further along the push isn't used
+            // twice
+            let moving: Vec<_> = to_move.iter().map(|(_, entity)| *entity).collect();
+            let mut moves = Vec::new();
+            let mut taken = Vec::new();
+            for (key, entity) in to_move.into_iter().rev() {
+                let from = match positions.get(entity) {
+                    Some(position) => *position,
+                    None => continue,
+                };
+                let mut to = match self.next(&from, key) {
+                    Some(to) => to,
+                    None => continue,
+                };
+
+                // Ice keeps things going the same way until something is in the
+                // way or the ice ends, the cells slid over are kept for events
+                let mut slid = Vec::new();
+                while grid
+                    .at(to.x, to.y)
+                    .iter()
+                    .any(|entity| ices.contains(*entity))
+                {
+                    let next = match self.next(&to, key) {
+                        Some(next) => next,
+                        None => break,
+                    };
+                    let is_player = players.contains(entity);
+                    let blocked = taken.contains(&(next.x, next.y))
+                        || grid.at(next.x, next.y).iter().any(|occupant| {
+                            immovables.contains(*occupant)
+                                || (movables.contains(*occupant) && !moving.contains(occupant))
+                                || gates
+                                    .get(*occupant)
+                                    .is_some_and(|gate| !gate.allows(direction(key), is_player))
+                                || (is_player && pits.get(*occupant).is_some_and(|pit| !pit.filled))
+                        });
+                    if blocked {
+                        break;
+                    }
+                    slid.push(to);
+                    to = next;
+                }
+
+                // Entering a teleporter comes out at its partner if nothing is
+                // there, otherwise it's a plain floor
+                let exit = grid
+                    .at(to.x, to.y)
+                    .iter()
+                    .find_map(|entity| teleporters.get(*entity))
+                    .and_then(|teleporter| teleporter.exit)
+                    .filter(|exit| {
+                        !taken.contains(exit)
+                            && !grid.at(exit.0, exit.1).iter().any(|entity| {
+                                movables.contains(*entity) || immovables.contains(*entity)
+                            })
+                    });
+                let entrance = to;
+                if let Some((x, y)) = exit {
+                    to.x = x;
+                    to.y = y;
                 }
+                // Only boxes are ever moved onto an open pit, they fall in
+                let pit = grid
+                    .at(to.x, to.y)
+                    .iter()
+                    .find(|entity| pits.get(**entity).is_some_and(|pit| !pit.filled))
+                    .copied();
+                taken.push((to.x, to.y));
+                moves.push((entity, from, slid, to, exit.map(|_| entrance), pit));
             }
 
-            event_queue
-                .events
-                .push(Event::EntityMoved(EntityMoved { id: id }));
+            // Now actually move what needs to be moved
+            let mut step = Step {
+                player: Some(player),
+                ..Step::default()
+            };
+            for (entity, from, slid, to, entrance, pit) in moves.into_iter().rev() {
+                if let Some(position) = positions.get_mut(entity) {
+                    for cell in slid.iter() {
+                        events.single_write(Event::EntityMoved(EntityMoved { entity, to: *cell }));
+                    }
+                    *position = to;
+                    grid.move_entity(entity, &from, &to);
+                    if pit.is_none() {
+                        step.moved.push((entity, from));
+                    }
+
+                    if players.contains(entity) {
+                        events.single_write(Event::PlayerMoved(PlayerMoved { entity, from, to }));
+                    }
+                    if let Some(entrance) = entrance {
+                        events.single_write(Event::Teleported(Teleported {
+                            entity,
+                            entrance,
+                            exit: to,
+                        }));
+                    }
+                    if let Some(the_box) = boxes.get(entity) {
+                        events.single_write(Event::BoxPushed(BoxPushed { entity, from, to }));
+                        step.pushes += 1;
+                        if spot_at(&grid, &box_spots, &from).is_some() {
+                            events.single_write(Event::BoxRemovedFromSpot(BoxRemovedFromSpot {
+                                entity,
+                            }));
+                        }
+                        if let Some(box_spot) = spot_at(&grid, &box_spots, &to) {
+                            events.single_write(Event::BoxPlacedOnSpot(BoxPlacedOnSpot {
+                                entity,
+                                is_correct_spot: box_spot.color.accepts(the_box.color),
+                            }));
+                        }
+                    }
+                }
+
+                events.single_write(Event::EntityMoved(EntityMoved { entity, to }));
+
+                // Moving onto a switch flips it
+                if (from.x, from.y) != (to.x, to.y) {
+                    let switch = grid
+                        .at(to.x, to.y)
+                        .iter()
+                        .find(|entity| switches.contains(**entity))
+                        .copied();
+                    if let Some(switch) = switch {
+                        flip(switch, &mut switches, &mut renderables);
+                        step.flipped.push(switch);
+                    }
+                }
+
+                // The box is deleted and the pit is a floor from now on
+                if let Some(pit) = pit {
+                    let color = boxes.get(entity).map(|the_box| the_box.color);
+                    let renderable = renderables.get(entity).cloned();
+                    if let (Some(color), Some(renderable)) = (color, renderable) {
+                        step.swallowed.push(Swallowed {
+                            entity,
+                            pit,
+                            position: from,
+                            color,
+                            renderable,
+                        });
+                    }
+                    grid.remove(entity, &to);
+                    let _ = entities.delete(entity);
+                    if let Some(filled) = pits.get_mut(pit) {
+                        filled.filled = true;
+                        if let Some(renderable) = renderables.get_mut(pit) {
+                            *renderable = Renderable::new_static(filled.image());
+                        }
+                    }
+                    events.single_write(Event::BoxFellInPit(BoxFellInPit { entity, pit: to }));
+                }
+            }
+            if !step.moved.is_empty() {
+                history.steps.push(step);
+            }
+        }
+    }
+}
+
+impl InputSystem {
+    // The cell next to `position` in the direction of `key`, if it's on the map
+    fn next(&self, position: &Position, key: KeyCode) -> Option<Position> {
+        let mut next = *position;
+        match key {
+            KeyCode::Up => next.y = position.y.checked_sub(1)?,
+            KeyCode::Down if position.y + 1 < self.rows => next.y += 1,
+            KeyCode::Left => next.x = position.x.checked_sub(1)?,
+            KeyCode::Right if position.x + 1 < self.cols => next.x += 1,
+            _ => return None,
         }
+        Some(next)
     }
 }
+
+fn flip(
+    entity: Entity,
+    switches: &mut WriteStorage<Switch>,
+    renderables: &mut WriteStorage<Renderable>,
+) {
+    if let Some(switch) = switches.get_mut(entity) {
+        switch.on = !switch.on;
+        if let Some(renderable) = renderables.get_mut(entity) {
+            *renderable = Renderable::new_static(switch.image());
+        }
+    }
+}
+
+// Which way a key moves, in LURD order like gate directions
+fn direction(key: KeyCode) -> usize {
+    match key {
+        KeyCode::Left => 0,
+        KeyCode::Up => 1,
+        KeyCode::Right => 2,
+        _ => 3,
+    }
+}
+
+fn spot_at<'s>(
+    grid: &Grid,
+    box_spots: &'s ReadStorage<BoxSpot>,
+    position: &Position,
+) -> Option<&'s BoxSpot> {
+    grid.at(position.x, position.y)
+        .iter()
+        .find_map(|entity| box_spots.get(*entity))
+}
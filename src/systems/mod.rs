@@ -1,9 +1,13 @@
+mod door_system;
 mod event_system;
 mod gameplay_state_system;
 mod input_system;
 mod rendering_system;
+mod stats_system;
 
+pub use self::door_system::DoorSystem;
 pub use self::event_system::EventSystem;
 pub use self::gameplay_state_system::GameplayStateSystem;
 pub use self::input_system::InputSystem;
 pub use self::rendering_system::RenderingSystem;
+pub use self::stats_system::StatsSystem;
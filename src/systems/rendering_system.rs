@@ -1,22 +1,22 @@
 use ggez::graphics::spritebatch::SpriteBatch;
 use ggez::{
-    graphics::{self, DrawParam, Font, Image, PxScale},
+    graphics::{self, Color, DrawParam, Image},
     Context,
 };
-use glam::Vec2;
 use itertools::Itertools;
-use specs::{Join, Read, ReadStorage, System};
+use specs::{Join, Read, ReadStorage, System, Write};
 use std::collections::HashMap;
 use std::time::Duration;
 
 use crate::components::*;
 use crate::constants::{MULTIPLIER, TEXT_PADDING, TEXT_SIZE, TILE_WIDTH};
 use crate::resources::*;
+use crate::ui::{draw_text, tile_param, TEXT_COLOR};
 
 pub struct RenderingSystem<'a> {
     pub context: &'a mut Context,
-    pub rows: u8,
-    pub cols: u8,
+    pub rows: Coord,
+    pub cols: Coord,
 }
 
 // System implementation
@@ -25,16 +25,15 @@ impl<'a> System<'a> for RenderingSystem<'a> {
     type SystemData = (
         Read<'a, Gameplay>,
         Read<'a, Time>,
+        Write<'a, ImagePaths>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, Renderable>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Active>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (gameplay, time, positions, renderables) = data;
-
-        // implementation here
-        // Clearing the screen (this gives us the background colour)
-        graphics::clear(self.context, graphics::Color::new(0.95, 0.95, 0.95, 1.0));
+        let (gameplay, time, mut image_paths, positions, renderables, players, actives) = data;
 
         // Get all the renderables with their positions and sort by the position z
         // This will allow us to have entities layered visually.
@@ -45,15 +44,13 @@ impl<'a> System<'a> for RenderingSystem<'a> {
         // and draw it at the specified position.
         for (position, renderable) in rendering_data.iter() {
             // Load the image
-            let image_path = self.get_image(renderable, time.delta);
+            let (image_path, color) = self.get_image(renderable, time.delta, &mut image_paths);
             let x = position.x as f32 * TILE_WIDTH * MULTIPLIER;
             let y = position.y as f32 * TILE_WIDTH * MULTIPLIER;
             let z = position.z;
 
             // draw
-            let draw_params = DrawParam::new()
-                .scale(Vec2::new(MULTIPLIER, MULTIPLIER))
-                .dest(Vec2::new(x, y));
+            let draw_params = tile_param(x, y, renderable.quarter_turns).color(color);
             rendering_batches
                 .entry(z)
                 .or_default()
@@ -62,6 +59,20 @@ impl<'a> System<'a> for RenderingSystem<'a> {
                 .push(draw_params);
         }
 
+        // Mark which player moves when there's more than one
+        if (&players).join().count() > 1 {
+            for (position, _) in (&positions, &actives).join() {
+                let x = position.x as f32 * TILE_WIDTH * MULTIPLIER;
+                let y = position.y as f32 * TILE_WIDTH * MULTIPLIER;
+                rendering_batches
+                    .entry(Active::Z)
+                    .or_default()
+                    .entry(Active::IMAGE.to_string())
+                    .or_default()
+                    .push(tile_param(x, y, 0));
+            }
+        }
+
         // Render any text
         self.draw_text(
             &gameplay.state.to_string(),
@@ -69,7 +80,7 @@ impl<'a> System<'a> for RenderingSystem<'a> {
             TILE_WIDTH * self.rows as f32 + TEXT_PADDING,
         );
         self.draw_text(
-            &gameplay.moves_count.to_string(),
+            &gameplay.moves_text(),
             TEXT_PADDING,
             TEXT_SIZE + TEXT_PADDING + (TILE_WIDTH * self.rows as f32),
         );
@@ -90,48 +101,27 @@ impl<'a> System<'a> for RenderingSystem<'a> {
                     .expect("expected render");
             }
         }
-        // Finally, present the context, this will actually display everything
-        // on the screen.
-        graphics::present(self.context).expect("expected to present");
     }
 }
 
 impl RenderingSystem<'_> {
     pub fn draw_text(&mut self, text_string: &str, x: f32, y: f32) {
-        let arcade_font = Font::new(self.context, "/ARCADE_N.TTF");
-        let mut text = graphics::Text::new(text_string);
-        text.set_font(arcade_font.unwrap(), PxScale::from(TEXT_SIZE));
-        let destination = Vec2::new(x * MULTIPLIER, y * MULTIPLIER);
-        let color = Some(graphics::Color::new(0.0, 0.0, 0.0, 1.0));
-        let dimensions = Vec2::new(0.0, 0.0);
-
-        graphics::queue_text(self.context, &text, dimensions, color);
-        graphics::draw_queued_text(
-            self.context,
-            graphics::DrawParam::new()
-                .scale(Vec2::new(MULTIPLIER, MULTIPLIER))
-                .dest(destination),
-            None,
-            graphics::FilterMode::Linear,
-        )
-        .expect("expected drawing queued text");
+        draw_text(self.context, text_string, x, y, TEXT_COLOR);
     }
-    pub fn get_image(&mut self, renderable: &Renderable, delta: Duration) -> String {
-        let path_index = match renderable.kind() {
-            RenderableKind::Static => {
-                // We only have one image, so we just return that
-                0
-            }
-            RenderableKind::Animated => {
-                // If we have multiple, we want to select the right one based on the delta time.
-                // First we get the delta in milliseconds, we % by 1000 to get the milliseconds
-                // only and finally we divide by 250 to get a number between 0 and 4. If it's 4
-                // we technically are on the next iteration of the loop (or on 0), but we will let
-                // the renderable handle this logic of wrapping frames.
-                ((delta.as_millis() % 1000) / 250) as usize
+    pub fn get_image(
+        &mut self,
+        renderable: &Renderable,
+        delta: Duration,
+        image_paths: &mut ImagePaths,
+    ) -> (String, Color) {
+        let path = renderable.frame(delta);
+        match &renderable.fallback {
+            // Colors without their own images use the neutral one, tinted
+            Some(fallback) if !image_paths.exists(self.context, &path) => {
+                let [r, g, b] = fallback.rgb;
+                (fallback.path.clone(), Color::from_rgb(r, g, b))
             }
-        };
-
-        renderable.path(path_index)
+            _ => (path, Color::WHITE),
+        }
     }
 }
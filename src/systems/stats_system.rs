@@ -0,0 +1,78 @@
+use specs::{
+    shrev::{EventChannel, ReaderId},
+    Read, ReadStorage, System, SystemData, World, Write,
+};
+
+use crate::{
+    components::Seat,
+    events::{Event, PlayerMoved, Undo},
+    resources::{Gameplay, SeatCount},
+};
+
+// Counts moves and pushes from the event stream
+#[derive(Default)]
+pub struct StatsSystem {
+    reader: Option<ReaderId<Event>>,
+    // The co-op player who moved last, boxes pushed are theirs
+    pusher: Option<usize>,
+}
+
+impl<'a> System<'a> for StatsSystem {
+    // Data
+    type SystemData = (
+        Read<'a, EventChannel<Event>>,
+        Write<'a, Gameplay>,
+        ReadStorage<'a, Seat>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<EventChannel<Event>>().register_reader());
+    }
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (events, mut gameplay, seats) = data;
+        let reader = self.reader.as_mut().expect("expected stats reader");
+
+        for event in events.read(reader) {
+            match event {
+                Event::LevelStarted => {
+                    gameplay.moves_count = 0;
+                    gameplay.pushes_count = 0;
+                    for count in gameplay.seats.iter_mut() {
+                        *count = SeatCount::default();
+                    }
+                }
+                Event::PlayerMoved(PlayerMoved { entity, .. }) => {
+                    gameplay.moves_count += 1;
+                    self.pusher = seats.get(*entity).map(|seat| seat.0);
+                    if let Some(count) = seat_count(&mut gameplay, self.pusher) {
+                        count.moves += 1;
+                    }
+                }
+                Event::BoxPushed(_) => {
+                    gameplay.pushes_count += 1;
+                    if let Some(count) = seat_count(&mut gameplay, self.pusher) {
+                        count.pushes += 1;
+                    }
+                }
+                Event::Undo(Undo { pushes, player }) => {
+                    gameplay.moves_count = gameplay.moves_count.saturating_sub(1);
+                    gameplay.pushes_count = gameplay.pushes_count.saturating_sub(*pushes);
+                    let seat = player
+                        .and_then(|player| seats.get(player))
+                        .map(|seat| seat.0);
+                    if let Some(count) = seat_count(&mut gameplay, seat) {
+                        count.moves = count.moves.saturating_sub(1);
+                        count.pushes = count.pushes.saturating_sub(*pushes);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn seat_count(gameplay: &mut Gameplay, seat: Option<usize>) -> Option<&mut SeatCount> {
+    gameplay.seats.get_mut(seat?)
+}
@@ -0,0 +1,230 @@
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, KeyCode as TermKey, KeyEvent, KeyEventKind},
+    execute, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ggez::event::KeyCode;
+use specs::WorldExt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::components::*;
+use crate::headless::Headless;
+use crate::map::{Level, Tile};
+use crate::resources::{Gameplay, Grid};
+
+const LEVELS_DIR: &str = "resources/levels";
+
+const WALL_COLOR: Color = Color::DarkGrey;
+const PLAYER_COLOR: Color = Color::White;
+const TELEPORTER_COLOR: Color = Color::Magenta;
+const ICE_COLOR: Color = Color::Cyan;
+const GATE_COLOR: Color = Color::Grey;
+const PIT_COLOR: Color = Color::DarkGrey;
+const MECHANISM_COLOR: Color = Color::Yellow;
+
+// A level being played in the terminal
+struct TerminalGame {
+    game: Headless,
+    level: Level,
+}
+
+impl TerminalGame {
+    fn new(level: &Level) -> Self {
+        Self {
+            game: Headless::new(level),
+            level: level.clone(),
+        }
+    }
+
+    fn press(&mut self, keycode: KeyCode) {
+        self.game.press(keycode);
+    }
+
+    fn is_won(&self) -> bool {
+        self.game.is_won()
+    }
+
+    // Each cell is two characters wide so the board looks square
+    fn draw(&self, out: &mut impl Write, title: &str) -> io::Result<()> {
+        let players = self.game.world.read_storage::<Player>();
+        let actives = self.game.world.read_storage::<Active>();
+        let boxes = self.game.world.read_storage::<Box>();
+        let box_spots = self.game.world.read_storage::<BoxSpot>();
+        let pits = self.game.world.read_storage::<Pit>();
+        let switches = self.game.world.read_storage::<Switch>();
+        let doors = self.game.world.read_storage::<Door>();
+        let grid = self.game.world.read_resource::<Grid>();
+        let gameplay = self.game.world.read_resource::<Gameplay>();
+
+        queue!(
+            out,
+            MoveTo(0, 0),
+            Clear(ClearType::All),
+            Print(title),
+            Print("\r\n\r\n")
+        )?;
+        for (y, row) in self.level.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let (x, y) = (x as Coord, y as Coord);
+                let occupants = grid.at(x, y);
+                let player = occupants.iter().find(|entity| players.contains(**entity));
+                let the_box = occupants.iter().find_map(|entity| boxes.get(*entity));
+                let box_spot = occupants.iter().find_map(|entity| box_spots.get(*entity));
+                let open_pit = occupants
+                    .iter()
+                    .filter_map(|entity| pits.get(*entity))
+                    .any(|pit| !pit.filled);
+                let switch = occupants.iter().find_map(|entity| switches.get(*entity));
+                let door = occupants.iter().find_map(|entity| doors.get(*entity));
+
+                let (text, color) = match (the_box, box_spot) {
+                    // The other players wait as `&` until Tab switches to them
+                    _ if player.is_some_and(|player| actives.contains(*player)) => {
+                        ("@ ", PLAYER_COLOR)
+                    }
+                    _ if player.is_some() => ("& ", PLAYER_COLOR),
+                    (Some(the_box), Some(box_spot)) if box_spot.color.accepts(the_box.color) => {
+                        ("◆ ", self.color(the_box.color))
+                    }
+                    (Some(the_box), _) => ("■ ", self.color(the_box.color)),
+                    (None, Some(box_spot)) => ("◇ ", self.color(box_spot.color)),
+                    (None, None) if *tile == Tile::Wall => ("██", WALL_COLOR),
+                    (None, None) if open_pit => ("▒▒", PIT_COLOR),
+                    (None, None) if door.is_some_and(|door| !door.open) => ("▐▌", MECHANISM_COLOR),
+                    (None, None) if door.is_some() => ("┆┆", MECHANISM_COLOR),
+                    (None, None) if tile.plate().is_some() => ("□ ", MECHANISM_COLOR),
+                    (None, None) if switch.is_some_and(|switch| switch.on) => {
+                        ("● ", MECHANISM_COLOR)
+                    }
+                    (None, None) if switch.is_some() => ("○ ", MECHANISM_COLOR),
+                    (None, None) if tile.teleporter().is_some() => ("◎ ", TELEPORTER_COLOR),
+                    (None, None) if tile.is_ice() => ("░░", ICE_COLOR),
+                    (None, None) if tile.gate().is_some() => (gate_text(tile), GATE_COLOR),
+                    (None, None) => ("  ", WALL_COLOR),
+                };
+                queue!(out, SetForegroundColor(color), Print(text))?;
+            }
+            queue!(out, ResetColor, Print("\r\n"))?;
+        }
+
+        queue!(
+            out,
+            Print(format!(
+                "\r\nMoves: {}  Pushes: {}  {}\r\n",
+                gameplay.moves_count, gameplay.pushes_count, gameplay.state
+            )),
+            Print("arrows/wasd move  tab switch player  u undo  r restart  n/p level  q quit\r\n")
+        )?;
+        out.flush()
+    }
+
+    fn color(&self, color: BoxColor) -> Color {
+        let [r, g, b] = self.level.palette.get(color).rgb;
+        Color::Rgb { r, g, b }
+    }
+}
+
+// One arrow for gates entered one way, a plus for any more
+fn gate_text(tile: &Tile) -> &'static str {
+    let directions = tile.gate().map(|gate| gate.directions).unwrap_or_default();
+    match directions {
+        [true, false, false, false] => "← ",
+        [false, true, false, false] => "↑ ",
+        [false, false, true, false] => "→ ",
+        [false, false, false, true] => "↓ ",
+        [true, false, true, false] => "↔ ",
+        [false, true, false, true] => "↕ ",
+        _ => "+ ",
+    }
+}
+
+// The levels shipped with the game, by file name
+pub fn load_levels() -> Vec<(String, Level)> {
+    let mut paths: Vec<_> = fs::read_dir(LEVELS_DIR)
+        .expect("expected levels directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+
+    paths.iter().map(|path| load_level(path)).collect()
+}
+
+pub fn load_level(path: &Path) -> (String, Level) {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let map = fs::read_to_string(path).expect("expected level file");
+    (name, Level::parse(&map))
+}
+
+// Play levels in the terminal, starting from `index`, until the player quits
+pub fn run(levels: &[(String, Level)], index: usize) -> io::Result<()> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+
+    let result = play(&mut out, levels, index);
+
+    execute!(out, ResetColor, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn play(out: &mut impl Write, levels: &[(String, Level)], mut index: usize) -> io::Result<()> {
+    let mut game = TerminalGame::new(&levels[index].1);
+
+    loop {
+        let title = format!(
+            "Level {} of {}: {}",
+            index + 1,
+            levels.len(),
+            levels[index].0
+        );
+        game.draw(out, &title)?;
+
+        let code = match event::read()? {
+            event::Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) => code,
+            _ => continue,
+        };
+
+        let keycode = match code {
+            TermKey::Left | TermKey::Char('a') => KeyCode::Left,
+            TermKey::Up | TermKey::Char('w') => KeyCode::Up,
+            TermKey::Right | TermKey::Char('d') => KeyCode::Right,
+            TermKey::Down | TermKey::Char('s') => KeyCode::Down,
+            TermKey::Char('u') => KeyCode::U,
+            TermKey::Tab => KeyCode::Tab,
+            TermKey::Char('r') => {
+                game = TerminalGame::new(&levels[index].1);
+                continue;
+            }
+            TermKey::Char('n') => {
+                index = (index + 1) % levels.len();
+                game = TerminalGame::new(&levels[index].1);
+                continue;
+            }
+            TermKey::Char('p') => {
+                index = (index + levels.len() - 1) % levels.len();
+                game = TerminalGame::new(&levels[index].1);
+                continue;
+            }
+            TermKey::Char('q') | TermKey::Esc => return Ok(()),
+            _ => continue,
+        };
+
+        // Moves stop once the level is won, until another is picked
+        if !game.is_won() {
+            game.press(keycode);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use ggez::{
+    graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, PxScale, Rect},
+    Context,
+};
+use glam::Vec2;
+
+use std::f32::consts::FRAC_PI_2;
+
+use crate::constants::{MULTIPLIER, TEXT_SIZE, TILE_WIDTH};
+
+pub const TEXT_COLOR: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+pub const HIGHLIGHT_COLOR: Color = Color::new(0.8, 0.2, 0.2, 1.0);
+
+// Draw a line of text. Coordinates are in unscaled pixels, like the tiles.
+pub fn draw_text(context: &mut Context, text_string: &str, x: f32, y: f32, color: Color) {
+    let arcade_font = Font::new(context, "/ARCADE_N.TTF");
+    let mut text = graphics::Text::new(text_string);
+    text.set_font(arcade_font.unwrap(), PxScale::from(TEXT_SIZE));
+    let destination = Vec2::new(x * MULTIPLIER, y * MULTIPLIER);
+    let dimensions = Vec2::new(0.0, 0.0);
+
+    graphics::queue_text(context, &text, dimensions, Some(color));
+    graphics::draw_queued_text(
+        context,
+        DrawParam::new()
+            .scale(Vec2::new(MULTIPLIER, MULTIPLIER))
+            .dest(destination),
+        None,
+        graphics::FilterMode::Linear,
+    )
+    .expect("expected drawing queued text");
+}
+
+// Where to draw a tile's image, turned clockwise by `quarter_turns`. Images
+// turn around their top left corner, so the destination moves to keep them
+// in the tile. Coordinates are in screen pixels.
+pub fn tile_param(x: f32, y: f32, quarter_turns: u8) -> DrawParam {
+    let size = TILE_WIDTH * MULTIPLIER;
+    let (dx, dy) = match quarter_turns % 4 {
+        1 => (size, 0.0),
+        2 => (size, size),
+        3 => (0.0, size),
+        _ => (0.0, 0.0),
+    };
+    DrawParam::new()
+        .scale(Vec2::new(MULTIPLIER, MULTIPLIER))
+        .rotation(quarter_turns as f32 * FRAC_PI_2)
+        .dest(Vec2::new(x + dx, y + dy))
+}
+
+// Draw a filled rectangle. Coordinates are in unscaled pixels, like the tiles.
+pub fn draw_rect(context: &mut Context, x: f32, y: f32, w: f32, h: f32, color: Color) {
+    let bounds = Rect::new(
+        x * MULTIPLIER,
+        y * MULTIPLIER,
+        w * MULTIPLIER,
+        h * MULTIPLIER,
+    );
+    let mesh =
+        Mesh::new_rectangle(context, DrawMode::fill(), bounds, color).expect("expected mesh");
+
+    graphics::draw(context, &mesh, DrawParam::new()).expect("expected render");
+}
+
+// Fade out whatever has been drawn so far, used below overlays
+pub fn draw_shade(context: &mut Context) {
+    let screen = graphics::screen_coordinates(context);
+    draw_rect(
+        context,
+        0.0,
+        0.0,
+        screen.w / MULTIPLIER,
+        screen.h / MULTIPLIER,
+        Color::new(0.95, 0.95, 0.95, 0.8),
+    );
+}
+
+// Convert a mouse position in window pixels to unscaled pixels
+pub fn unscale(x: f32, y: f32) -> (f32, f32) {
+    (x / MULTIPLIER, y / MULTIPLIER)
+}
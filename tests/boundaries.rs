@@ -0,0 +1,157 @@
+use ggez::event::KeyCode;
+use rust_sokoban::components::{
+    register_components, Active, Box, BoxColor, Coord, Player, Position,
+};
+use rust_sokoban::entities::{create_box, create_player, create_wall};
+use rust_sokoban::map::{load_map, Level};
+use rust_sokoban::palette::Palette;
+use rust_sokoban::resources::{register_resources, InputQueue};
+use rust_sokoban::systems::InputSystem;
+use specs::{Join, RunNow, World, WorldExt};
+
+fn world() -> World {
+    let mut world = World::new();
+    register_components(&mut world);
+    register_resources(&mut world);
+    world
+}
+
+fn press(world: &mut World, system: &mut InputSystem, key: KeyCode) {
+    world
+        .write_resource::<InputQueue>()
+        .keys_pressed
+        .push_back(key);
+    system.run_now(world);
+    world.maintain();
+}
+
+fn player_at(world: &World) -> (Coord, Coord) {
+    let positions = world.read_storage::<Position>();
+    let players = world.read_storage::<Player>();
+    let actives = world.read_storage::<Active>();
+    let (position, _, _) = (&positions, &players, &actives).join().next().unwrap();
+    (position.x, position.y)
+}
+
+fn boxes_at(world: &World) -> Vec<(Coord, Coord)> {
+    let positions = world.read_storage::<Position>();
+    let boxes = world.read_storage::<Box>();
+    (&positions, &boxes)
+        .join()
+        .map(|(position, _)| (position.x, position.y))
+        .collect()
+}
+
+#[test]
+fn wide_levels_load_and_push_past_255() {
+    let width = 300;
+    let mut row = vec!["."; width];
+    row[width - 4] = "P";
+    row[width - 3] = "B*";
+    let walls = vec!["W"; width + 2].join(" ");
+    let map = format!("{}\nW {} W\n{}\n", walls, row.join(" "), walls);
+    let level = Level::parse(&map);
+
+    let mut world = world();
+    let (rows, cols) = load_map(&mut world, &level);
+    assert_eq!((rows, cols), (3, width as Coord + 2));
+    let mut system = InputSystem { rows, cols };
+
+    press(&mut world, &mut system, KeyCode::Right);
+    assert_eq!(player_at(&world), (width as Coord - 2, 1));
+    assert_eq!(boxes_at(&world), vec![(width as Coord - 1, 1)]);
+}
+
+#[test]
+fn tall_levels_load_and_push_past_255() {
+    let height = 300;
+    let mut map = String::from("W W W\n");
+    for y in 0..height {
+        let tile = match y {
+            y if y == height - 4 => "P",
+            y if y == height - 3 => "B*",
+            _ => ".",
+        };
+        map.push_str(&format!("W {} W\n", tile));
+    }
+    map.push_str("W W W\n");
+    let level = Level::parse(&map);
+
+    let mut world = world();
+    let (rows, cols) = load_map(&mut world, &level);
+    assert_eq!((rows, cols), (height as Coord + 2, 3));
+    let mut system = InputSystem { rows, cols };
+
+    press(&mut world, &mut system, KeyCode::Down);
+    assert_eq!(player_at(&world), (1, height as Coord - 2));
+    assert_eq!(boxes_at(&world), vec![(1, height as Coord - 1)]);
+}
+
+#[test]
+fn boxes_stop_at_the_edge_of_the_map() {
+    // No walls around it, the edge of the map is all that holds the box
+    let level = Level::parse("P B*\n. .\n");
+    let mut world = world();
+    let (rows, cols) = load_map(&mut world, &level);
+    let mut system = InputSystem { rows, cols };
+
+    press(&mut world, &mut system, KeyCode::Right);
+    assert_eq!(player_at(&world), (0, 0));
+    assert_eq!(boxes_at(&world), vec![(1, 0)]);
+
+    press(&mut world, &mut system, KeyCode::Up);
+    assert_eq!(player_at(&world), (0, 0));
+
+    press(&mut world, &mut system, KeyCode::Left);
+    assert_eq!(player_at(&world), (0, 0));
+
+    press(&mut world, &mut system, KeyCode::Down);
+    press(&mut world, &mut system, KeyCode::Down);
+    assert_eq!(player_at(&world), (0, 1));
+}
+
+#[test]
+fn positions_near_the_largest_coordinate() {
+    let (x, y) = (Coord::MAX - 3, Coord::MAX - 3);
+    let mut world = world();
+    let color = Palette::default().get(BoxColor::ANY);
+    create_player(&mut world, Position { x, y, z: 0 });
+    create_box(&mut world, Position { x: x + 1, y, z: 0 }, &color);
+    create_wall(&mut world, Position { x, y: y - 1, z: 0 });
+    let player = {
+        let players = world.read_storage::<Player>();
+        (&world.entities(), &players).join().next().unwrap().0
+    };
+    world
+        .write_storage::<Active>()
+        .insert(player, Active)
+        .unwrap();
+    let mut system = InputSystem {
+        rows: Coord::MAX,
+        cols: Coord::MAX,
+    };
+
+    // The box is pushed onto the last column and no further
+    press(&mut world, &mut system, KeyCode::Right);
+    assert_eq!(player_at(&world), (x + 1, y));
+    assert_eq!(boxes_at(&world), vec![(x + 2, y)]);
+    press(&mut world, &mut system, KeyCode::Right);
+    assert_eq!(player_at(&world), (x + 1, y));
+
+    // Down onto the last row, then off the bottom of the map is refused
+    press(&mut world, &mut system, KeyCode::Down);
+    press(&mut world, &mut system, KeyCode::Down);
+    press(&mut world, &mut system, KeyCode::Down);
+    assert_eq!(player_at(&world), (x + 1, y + 2));
+
+    // Back up past the wall's row without walking the whole column, and
+    // into the wall from above
+    for _ in 0..5 {
+        press(&mut world, &mut system, KeyCode::Up);
+    }
+    assert_eq!(player_at(&world), (x + 1, y - 3));
+    press(&mut world, &mut system, KeyCode::Left);
+    press(&mut world, &mut system, KeyCode::Down);
+    press(&mut world, &mut system, KeyCode::Down);
+    assert_eq!(player_at(&world), (x, y - 2));
+}
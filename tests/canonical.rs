@@ -0,0 +1,100 @@
+use rust_sokoban::canonical::{canonical_form, level_hash, level_hash_symmetric};
+use rust_sokoban::map::Level;
+
+const LEVEL: &str = "
+W W W W W
+W P . . W
+W . B* S* W
+W W W W W
+";
+
+#[test]
+fn trimmed_copies_hash_the_same() {
+    let padded = "
+N N N N N N N
+N W W W W W N
+N W P . . W .
+N W . B* S* W .
+N W W W W W N
+. . . . . . .
+";
+    assert_eq!(
+        level_hash(&Level::parse(LEVEL)),
+        level_hash(&Level::parse(padded))
+    );
+}
+
+#[test]
+fn respaced_copies_hash_the_same() {
+    let respaced = "\n\n  W   W W\tW W  \nW P . .   W\n W . B*  S* W\nW W W W W   \n\n";
+    assert_eq!(
+        level_hash(&Level::parse(LEVEL)),
+        level_hash(&Level::parse(respaced))
+    );
+}
+
+#[test]
+fn rotated_and_mirrored_copies_hash_the_same_symmetrically() {
+    let rotated = "
+W W W W
+W . P W
+W B* . W
+W S* . W
+W W W W
+";
+    let mirrored = "
+W W W W W
+W . . P W
+W S* B* . W
+W W W W W
+";
+    let level = Level::parse(LEVEL);
+    for other in [rotated, mirrored] {
+        let other = Level::parse(other);
+        assert_ne!(level_hash(&level), level_hash(&other));
+        assert_eq!(level_hash_symmetric(&level), level_hash_symmetric(&other));
+    }
+}
+
+#[test]
+fn gates_turn_with_rotated_and_mirrored_copies() {
+    let level = Level::parse("W W W W W W\nW P B* G> S* W\nW W W W W W");
+    let rotated = Level::parse("W W W\nW P W\nW B* W\nW Gv W\nW S* W\nW W W");
+    let mirrored = Level::parse("W W W W W W\nW S* G< B* P W\nW W W W W W");
+    for other in [rotated, mirrored] {
+        assert_eq!(level_hash_symmetric(&level), level_hash_symmetric(&other));
+    }
+
+    // A gate left pointing the same way lets the box through the other way
+    let unturned = Level::parse("W W W W W W\nW S* G> B* P W\nW W W W W W");
+    assert_ne!(
+        level_hash_symmetric(&level),
+        level_hash_symmetric(&unturned)
+    );
+}
+
+#[test]
+fn sokoban_format_reads_as_neutral_tokens() {
+    let xsb = "
+  #####
+###   #
+# $*+ #
+#  .  #
+#######
+";
+    let tokens = "
+N N W W W W W
+W W W . . . W
+W . B* B*S* PS* . W
+W . . S* . . W
+W W W W W W W
+";
+    assert_eq!(
+        canonical_form(&Level::parse(xsb)),
+        canonical_form(&Level::parse(tokens))
+    );
+    assert_eq!(
+        level_hash(&Level::parse(xsb)),
+        level_hash(&Level::parse(tokens))
+    );
+}
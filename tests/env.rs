@@ -0,0 +1,35 @@
+use rust_sokoban::env::{Action, RewardConfig, SokobanEnv};
+use rust_sokoban::map::Level;
+
+#[test]
+fn resetting_to_a_level_without_a_player_is_an_error() {
+    let mut env = SokobanEnv::new(RewardConfig::default(), vec![]);
+    let level = Level::parse("W W W W\nW B* S* W\nW W W W\n");
+    assert!(env.reset(&level).is_err());
+
+    let level = Level::parse("W W W W W\nW P B* S* W\nW W W W W\n");
+    assert!(env.reset(&level).is_ok());
+}
+
+#[test]
+fn spare_boxes_can_be_left_on_dead_squares() {
+    let mut env = SokobanEnv::new(RewardConfig::default(), vec![]);
+    env.reset(&Level::parse(
+        "W W W W W W W\nW . B* P B* S* W\nW W W W W W W",
+    ))
+    .expect("expected a player");
+    let (_, _, done, info) = env.step(Action::Left);
+    assert!(!done);
+    assert!(!info.deadlocked);
+    env.step(Action::Right);
+    let (_, _, done, info) = env.step(Action::Right);
+    assert!(done);
+    assert!(info.solved);
+
+    // The only box left in a corner is still a deadlock
+    env.reset(&Level::parse("W W W W W W\nW . B* P S* W\nW W W W W W"))
+        .expect("expected a player");
+    let (_, _, done, info) = env.step(Action::Left);
+    assert!(done);
+    assert!(info.deadlocked);
+}
@@ -0,0 +1,34 @@
+use rand::{rngs::StdRng, SeedableRng};
+use rust_sokoban::generator::{generate, GeneratorConfig};
+use rust_sokoban::solver::solve;
+
+#[test]
+fn generated_levels_have_the_requested_size_and_can_be_solved() {
+    let config = GeneratorConfig {
+        rows: 7,
+        cols: 8,
+        boxes: 2,
+        colors: 2,
+        attempts: 10,
+    };
+    for seed in 0..5 {
+        let generated =
+            generate(&config, &mut StdRng::seed_from_u64(seed)).expect("expected a level");
+        let level = generated.level;
+
+        assert_eq!((level.rows(), level.cols()), (7, 8));
+        let boxes = level
+            .tiles
+            .iter()
+            .flatten()
+            .filter(|tile| tile.box_color().is_some())
+            .count();
+        assert_eq!(boxes, 2);
+        assert!(
+            solve(&level, 100_000).is_ok_and(|solution| solution.is_some()),
+            "seed {} has no solution\n{}",
+            seed,
+            level.to_map_string()
+        );
+    }
+}
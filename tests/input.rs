@@ -0,0 +1,146 @@
+use ggez::event::KeyCode;
+use rust_sokoban::entities::seat_players;
+use rust_sokoban::events::{EntityMoved, Event};
+use rust_sokoban::headless::Headless;
+use rust_sokoban::map::{snapshot, Level};
+use rust_sokoban::resources::{Gameplay, InputQueue};
+use rust_sokoban::systems::InputSystem;
+use specs::{shrev::EventChannel, RunNow, WorldExt};
+
+// Load `map`, play `keys` through the game's systems one at a time and read
+// the level back as a map
+fn play(map: &str, keys: &[KeyCode]) -> String {
+    let mut game = Headless::new(&Level::parse(map));
+    for key in keys {
+        game.press(*key);
+    }
+    snapshot(&game.world).to_map_string()
+}
+
+#[test]
+fn walls_stop_the_player_and_boxes() {
+    let map = "W W W W\nW P B* W\nW W W W";
+    assert_eq!(play(map, &[KeyCode::Right, KeyCode::Up]), map);
+}
+
+#[test]
+fn boxes_pushed_onto_goals_stay_on_them() {
+    let map = "W W W W W\nW P B* S* W\nW W W W W";
+    assert_eq!(
+        play(map, &[KeyCode::Right]),
+        "W W W W W\nW . P B*S* W\nW W W W W"
+    );
+    assert_eq!(play(map, &[KeyCode::Right, KeyCode::U]), map);
+}
+
+#[test]
+fn colored_boxes_keep_their_colors_and_palette() {
+    let map = "color X teal 30a0a0\nW W W W W W\nW P BX SR SX W\nW . BR . . W\nW W W W W W";
+    assert_eq!(
+        play(map, &[KeyCode::Right, KeyCode::Right]),
+        "color X teal 30a0a0\nW W W W W W\nW . . PSR BXSX W\nW . BR . . W\nW W W W W W"
+    );
+}
+
+#[test]
+fn the_player_walks_over_goals() {
+    let map = "W W W W W\nW P S* . W\nW W W W W";
+    assert_eq!(
+        play(map, &[KeyCode::Right]),
+        "W W W W W\nW . PS* . W\nW W W W W"
+    );
+    assert_eq!(
+        play(map, &[KeyCode::Right, KeyCode::Right]),
+        "W W W W W\nW . S* P W\nW W W W W"
+    );
+}
+
+#[test]
+fn queued_keys_are_played_oldest_first() {
+    let mut game = Headless::new(&Level::parse("W W W W W\nW . . . W\nW . P . W\nW W W W W"));
+    let mut input = InputSystem {
+        rows: game.rows(),
+        cols: game.cols(),
+    };
+
+    // One key is played per run, up goes first
+    game.world
+        .write_resource::<InputQueue>()
+        .keys_pressed
+        .extend([KeyCode::Up, KeyCode::Left, KeyCode::Down]);
+    input.run_now(&game.world);
+    game.world.maintain();
+    assert_eq!(
+        snapshot(&game.world).to_map_string(),
+        "W W W W W\nW . P . W\nW . . . W\nW W W W W"
+    );
+    input.run_now(&game.world);
+    input.run_now(&game.world);
+    game.world.maintain();
+    assert_eq!(
+        snapshot(&game.world).to_map_string(),
+        "W W W W W\nW . . . W\nW P . . W\nW W W W W"
+    );
+}
+
+#[test]
+fn sliding_over_ice_reports_every_cell() {
+    let mut game = Headless::new(&Level::parse("W W W W W W\nW P I I . W\nW W W W W W"));
+    let mut reader = game
+        .world
+        .write_resource::<EventChannel<Event>>()
+        .register_reader();
+    game.press(KeyCode::Right);
+
+    let events = game.world.read_resource::<EventChannel<Event>>();
+    let cells: Vec<_> = events
+        .read(&mut reader)
+        .filter_map(|event| match event {
+            Event::EntityMoved(EntityMoved { to, .. }) => Some((to.x, to.y)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(cells, vec![(2, 1), (3, 1), (4, 1)]);
+}
+
+#[test]
+fn too_few_boxes_for_the_goals_is_stuck() {
+    let mut game = Headless::new(&Level::parse(
+        "W W W W W W\nW P B* H S* W\nW . B* . S* W\nW W W W W W",
+    ));
+    assert_eq!(
+        game.world.read_resource::<Gameplay>().state.to_string(),
+        "Playing"
+    );
+    game.press(KeyCode::Right);
+    assert_eq!(
+        game.world.read_resource::<Gameplay>().state.to_string(),
+        "Stuck"
+    );
+    game.press(KeyCode::U);
+    assert_eq!(
+        game.world.read_resource::<Gameplay>().state.to_string(),
+        "Playing"
+    );
+}
+
+#[test]
+fn keys_for_empty_seats_are_dropped() {
+    let mut game = Headless::new(&Level::parse("W W W W\nW P . W\nW W W W"));
+    assert_eq!(seat_players(&mut game.world, 2), 1);
+
+    game.world
+        .write_resource::<InputQueue>()
+        .seat_keys
+        .extend([(1, KeyCode::Left), (0, KeyCode::Right)]);
+    game.press(KeyCode::Escape);
+    assert!(game
+        .world
+        .read_resource::<InputQueue>()
+        .seat_keys
+        .is_empty());
+    assert_eq!(
+        snapshot(&game.world).to_map_string(),
+        "W W W W\nW . P W\nW W W W"
+    );
+}
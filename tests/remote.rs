@@ -0,0 +1,65 @@
+use rust_sokoban::env::Action;
+use rust_sokoban::remote::{Command, RemoteServer};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Wait for the server to receive `count` commands, failing if they don't
+// arrive in time
+fn poll(server: &RemoteServer, count: usize) -> Vec<Command> {
+    let start = Instant::now();
+    let mut commands = vec![];
+    while commands.len() < count && start.elapsed() < Duration::from_secs(5) {
+        commands.extend(server.poll().into_iter().map(|(_, command)| command));
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(commands.len(), count, "expected {} commands", count);
+    commands
+}
+
+fn read_message(reader: &mut impl BufRead) -> Value {
+    let mut line = String::new();
+    reader.read_line(&mut line).expect("expected a line");
+    serde_json::from_str(&line).expect("expected JSON")
+}
+
+#[test]
+fn loopback_client_sends_commands_and_gets_messages() {
+    let server = RemoteServer::start("127.0.0.1:0").expect("expected to listen");
+    let mut client = TcpStream::connect(server.address()).expect("expected to connect");
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+
+    client
+        .write_all(b"{\"command\": \"move\", \"direction\": \"left\"}\n")
+        .unwrap();
+    client
+        .write_all(b"{\"command\": \"move\", \"direction\": \"up\", \"seat\": 1}\n")
+        .unwrap();
+    let commands = poll(&server, 2);
+    assert!(matches!(
+        commands[0],
+        Command::Move {
+            direction: Action::Left,
+            seat: None
+        }
+    ));
+    assert!(matches!(
+        commands[1],
+        Command::Move {
+            direction: Action::Up,
+            seat: Some(1)
+        }
+    ));
+
+    // The client is registered by the time its commands arrive
+    server.broadcast(&json!({ "type": "event", "event": "level_started" }));
+    assert_eq!(read_message(&mut reader)["event"], "level_started");
+
+    client.write_all(b"not a command\n").unwrap();
+    assert_eq!(read_message(&mut reader)["type"], "error");
+}
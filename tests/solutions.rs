@@ -0,0 +1,117 @@
+use rust_sokoban::env::{Action, RewardConfig, SokobanEnv};
+use rust_sokoban::map::Level;
+use rust_sokoban::solver::solve;
+
+// Solve `map` and play the solution with the game's own systems, which have
+// to agree that it's solved
+fn assert_solution_wins(map: &str) {
+    let level = Level::parse(map);
+    let solution = solve(&level, 100_000)
+        .expect("expected one player")
+        .expect("expected a solution");
+
+    let config = RewardConfig {
+        max_steps: None,
+        terminate_on_deadlock: false,
+        ..RewardConfig::default()
+    };
+    let mut env = SokobanEnv::new(config, vec![]);
+    env.reset(&level).expect("expected a player");
+    for c in solution.chars() {
+        env.step(Action::from_lurd(c).expect("expected a LURD move"));
+    }
+    assert!(env.info().solved, "{} doesn't solve\n{}", solution, map);
+}
+
+#[test]
+fn teleporters() {
+    assert_solution_wins(
+        "
+W W W W W W W W W
+W . . . W . . . W
+W P B* T1 W . T1 S* W
+W . . . W . . . W
+W . . . . . . . W
+W W W W W W W W W
+",
+    );
+}
+
+#[test]
+fn ice() {
+    assert_solution_wins(
+        "
+W W W W W W W
+W P B* I I S* W
+W . . . . . W
+W W W W W W W
+",
+    );
+}
+
+#[test]
+fn gates() {
+    assert_solution_wins(
+        "
+W W W W W W W
+W . . G^ . . W
+W P B* G> . S* W
+W . . . . . W
+W W W W W W W
+",
+    );
+}
+
+#[test]
+fn pits() {
+    assert_solution_wins(
+        "
+W W W W W W W
+W P B* H B* S* W
+W W W W W W W
+",
+    );
+}
+
+#[test]
+fn plates_and_doors() {
+    assert_solution_wins(
+        "
+W W W W W W W W
+W . B* O1 W . . W
+W P B* . D1 . S* W
+W . . . W . . W
+W W W W W W W W
+",
+    );
+}
+
+#[test]
+fn switches_and_doors() {
+    assert_solution_wins(
+        "
+W W W W W W W
+W . X1 W W W W
+W P B* . D1 S* W
+W W W W W W W
+",
+    );
+}
+
+#[test]
+fn campaign_levels_for_one_player() {
+    for index in 1..=4 {
+        let path = format!(
+            "{}/resources/levels/0{}.txt",
+            env!("CARGO_MANIFEST_DIR"),
+            index
+        );
+        assert_solution_wins(&std::fs::read_to_string(path).expect("expected the level"));
+    }
+}
+
+#[test]
+fn levels_with_several_players_are_turned_away() {
+    let level = Level::parse("W W W W W W\nW P B* S* P W\nW W W W W W");
+    assert!(solve(&level, 100_000).is_err());
+}
@@ -0,0 +1,33 @@
+use ggez::event::KeyCode;
+use rust_sokoban::components::register_components;
+use rust_sokoban::map::{load_map, Level};
+use rust_sokoban::resources::{register_resources, Gameplay, InputQueue};
+use rust_sokoban::systems::{InputSystem, StatsSystem};
+use specs::{RunNow, System, World, WorldExt};
+
+#[test]
+fn undoing_a_push_of_two_boxes_takes_back_both() {
+    let level = Level::parse("W W W W W W\nW P B* B* . W\nW W W W W W\n");
+    let mut world = World::new();
+    register_components(&mut world);
+    register_resources(&mut world);
+    let (rows, cols) = load_map(&mut world, &level);
+    let mut input = InputSystem { rows, cols };
+    let mut stats = StatsSystem::default();
+    System::setup(&mut stats, &mut world);
+
+    let mut press = |world: &mut World, key: KeyCode| {
+        world
+            .write_resource::<InputQueue>()
+            .keys_pressed
+            .push_back(key);
+        input.run_now(world);
+        stats.run_now(world);
+        world.maintain();
+        let gameplay = world.read_resource::<Gameplay>();
+        (gameplay.moves_count, gameplay.pushes_count)
+    };
+
+    assert_eq!(press(&mut world, KeyCode::Right), (1, 2));
+    assert_eq!(press(&mut world, KeyCode::U), (0, 0));
+}
@@ -0,0 +1,43 @@
+use rust_sokoban::map::Level;
+use rust_sokoban::svg::{level_svg, SvgOptions};
+
+#[test]
+fn ids_are_escaped() {
+    let level = Level::parse("W W W W W\nW P T< T< W\nW O& D& . W\nW W W W W");
+    let svg = level_svg(&level, &SvgOptions::default());
+    assert!(svg.contains(">&lt;</text>"));
+    assert!(svg.contains(">&amp;</text>"));
+    assert!(!svg.contains("><</text>"));
+    assert!(!svg.contains(">&</text>"));
+}
+
+fn path_lines(map: &str, solution: &str) -> Vec<String> {
+    let options = SvgOptions {
+        solution: Some(solution.to_string()),
+        ..SvgOptions::default()
+    };
+    level_svg(&Level::parse(map), &options)
+        .lines()
+        .filter(|line| line.starts_with("<polyline"))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[test]
+fn slides_over_ice_stay_on_one_line() {
+    let lines = path_lines("W W W W W W\nW P I I . W\nW W W W W W", "r");
+    assert_eq!(lines.len(), 1);
+    assert!(
+        lines[0].contains(r#"points="48,48 144,48""#),
+        "{}",
+        lines[0]
+    );
+}
+
+#[test]
+fn teleports_start_a_new_line() {
+    let lines = path_lines("W W W W W W\nW P T1 W T1 W\nW W W W W W", "r");
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#"points="48,48 80,48""#), "{}", lines[0]);
+    assert!(lines[1].contains(r#"points="144,48""#), "{}", lines[1]);
+}